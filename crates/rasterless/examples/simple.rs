@@ -3,18 +3,18 @@ use rasterless::{Ellipsoid, RasterlessRenderPlugin};
 
 fn main() {
     App::new()
+        // Every pixel is marched once, so multisampling gains nothing
+        .insert_resource(Msaa::Off)
         .add_plugins((DefaultPlugins, RasterlessRenderPlugin))
         .add_systems(Startup, setup)
         .run();
 }
 
 fn setup(mut commands: Commands) {
-    let test = Vec::<u32>::new();
-
-    /*commands.spawn(Camera3dBundle {
+    commands.spawn(Camera3dBundle {
         transform: Transform::from_xyz(10., 10., 10.).looking_at(Vec3::ZERO, Vec3::Y),
         ..default()
     });
 
-    commands.spawn((SpatialBundle::default(), Ellipsoid { radius: 5. }));*/
+    commands.spawn((SpatialBundle::default(), Ellipsoid { radius: 5. }));
 }
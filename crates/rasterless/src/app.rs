@@ -1,7 +1,11 @@
-use crate::graphics::Graphics;
+use crate::{controls::DemoControls, graphics::Graphics, light::Light, primitive::Primitive};
+use glam::Vec3;
 use log::{error, warn};
 use winit::{
-    application::ApplicationHandler, event::WindowEvent, event_loop::ActiveEventLoop,
+    application::ApplicationHandler,
+    event::{ElementState, WindowEvent},
+    event_loop::ActiveEventLoop,
+    keyboard::PhysicalKey,
     window::WindowId,
 };
 
@@ -9,6 +13,7 @@ pub(crate) struct AppState {
     render_attempts: u8,
     init_attempts: u8,
     graphics: Option<Graphics>,
+    controls: DemoControls,
 }
 
 impl AppState {
@@ -17,6 +22,7 @@ impl AppState {
             render_attempts: 0,
             init_attempts: 0,
             graphics: None,
+            controls: DemoControls::new(),
         }
     }
 }
@@ -25,12 +31,12 @@ impl ApplicationHandler for AppState {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         if self.graphics.is_none() {
             let graphics = pollster::block_on(Graphics::init(event_loop));
-            if graphics.is_err() {
+            if let Err(error) = &graphics {
                 self.init_attempts += 1;
                 if self.init_attempts < 3 {
                     warn!(
-                        "Failed to initialize the GPU device on try {}. Will try again.",
-                        self.init_attempts
+                        "Failed to initialize the GPU device on try {} ({}). Will try again.",
+                        self.init_attempts, error
                     );
                 } else {
                     error!(
@@ -39,7 +45,33 @@ impl ApplicationHandler for AppState {
                     event_loop.exit();
                 }
             } else {
-                self.graphics = Some(graphics.unwrap());
+                let mut graphics = graphics.unwrap();
+
+                // Light the scene with a single white light, above and behind the camera
+                graphics.set_lights(&[Light::new(Vec3::new(2.0, 4.0, -3.0), Vec3::ONE, 25.0)]);
+
+                // Place a sphere, a box, and an ellipsoid on a ground plane. The sphere comes first,
+                // where the demo controls expect it.
+                graphics.set_primitives(&[
+                    Primitive::Sphere {
+                        center: Vec3::ZERO,
+                        radius: 1.0,
+                    },
+                    Primitive::Ellipsoid {
+                        center: Vec3::new(-2.0, 0.0, 0.5),
+                        radii: Vec3::new(0.5, 1.0, 0.5),
+                    },
+                    Primitive::Box {
+                        center: Vec3::new(2.0, -0.25, 0.5),
+                        half_extents: Vec3::splat(0.75),
+                    },
+                    Primitive::Plane {
+                        normal: Vec3::Y,
+                        distance: -1.0,
+                    },
+                ]);
+
+                self.graphics = Some(graphics);
             }
         }
     }
@@ -59,6 +91,16 @@ impl ApplicationHandler for AppState {
                     warn!("Resize requested before graphics context has been successfully initialized!");
                 }
             }
+            WindowEvent::KeyboardInput { event, .. } => {
+                if event.state != ElementState::Pressed || event.repeat {
+                    return;
+                }
+                if let (Some(graphics), PhysicalKey::Code(code)) =
+                    (self.graphics.as_mut(), event.physical_key)
+                {
+                    self.controls.key_pressed(graphics, code);
+                }
+            }
             WindowEvent::RedrawRequested => {
                 if let Some(graphics) = &self.graphics {
                     let render_result = graphics.draw();
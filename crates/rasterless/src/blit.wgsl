@@ -0,0 +1,17 @@
+// Copies the output of the compute pass to the surface texture. This pass is only used on adapters
+// which cannot write to the surface texture from a compute shader.
+
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+
+@vertex
+fn vertex(@builtin(vertex_index) index: u32) -> @builtin(position) vec4f {
+    // A single triangle which covers the whole screen
+    let corner = vec2f(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4f(corner * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fragment(@builtin(position) position: vec4f) -> @location(0) vec4f {
+    return textureLoad(source, vec2i(position.xy), 0);
+}
@@ -1,14 +1,61 @@
-use bytemuck::cast_slice;
-use encase::{ShaderType, UniformBuffer};
+use bytemuck::{Pod, Zeroable};
+#[cfg(test)]
+use glam::Quat;
 use glam::{Vec2, Vec3};
 use winit::dpi::PhysicalSize;
 
-#[derive(Clone, Copy, ShaderType)]
+/// The `CameraUniform` struct is the data uploaded to the camera uniform buffer. Its layout must
+/// match the `Camera` struct in `compute.wgsl` byte for byte. WGSL aligns each `vec3f` to 16 bytes,
+/// so every `vec3f` is followed by a scalar which fills the remaining 4 bytes, and the struct is
+/// padded up to a multiple of 16 bytes at the end.
+///
+/// | Offset | Field        | WGSL type |
+/// |--------|--------------|-----------|
+/// | 0      | `position`   | `vec3f`   |
+/// | 12     | `size`       | `f32`     |
+/// | 16     | `forward`    | `vec3f`   |
+/// | 28     | `aspect`     | `f32`     |
+/// | 32     | `up`         | `vec3f`   |
+/// | 44     | `near`       | `f32`     |
+/// | 48     | `resolution` | `vec2f`   |
+/// | 56     | `far`        | `f32`     |
+/// | 60     | `projection` | `u32`     |
+///
+/// The `projection` field is 0 for a perspective projection, in which case `size` is the field of
+/// view in degrees, and 1 for an orthographic projection, in which case `size` is the height of the
+/// view in world units.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub(crate) struct CameraUniform {
+    pub(crate) position: [f32; 3],
+    pub(crate) size: f32,
+    pub(crate) forward: [f32; 3],
+    pub(crate) aspect: f32,
+    pub(crate) up: [f32; 3],
+    pub(crate) near: f32,
+    pub(crate) resolution: [f32; 2],
+    pub(crate) far: f32,
+    pub(crate) projection: u32,
+}
+
+/// The `CameraProjection` enum describes how a `Camera` casts rays through the pixels of the screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum CameraProjection {
+    /// Rays spread out from the camera's position, covering the given field of view in degrees
+    /// along the larger dimension of the screen.
+    Perspective { fov: f32 },
+    /// Rays run parallel to the camera's direction, starting from points spread across a view plane
+    /// of the given height in world units.
+    Orthographic { height: f32 },
+}
+
+#[derive(Clone, Copy)]
 pub(crate) struct Camera {
     position: Vec3,
     direction: Vec3,
     up: Vec3,
-    fov: f32,
+    projection: CameraProjection,
+    near: f32,
     far: f32,
     screen: Vec2,
 }
@@ -18,7 +65,8 @@ impl Camera {
         position: Vec3,
         direction: Vec3,
         up: Vec3,
-        fov: f32,
+        projection: CameraProjection,
+        near: f32,
         far: f32,
         screen: PhysicalSize<u32>,
     ) -> Self {
@@ -26,31 +74,218 @@ impl Camera {
             position,
             direction,
             up,
-            fov,
+            projection,
+            near,
             far,
             screen: Vec2::new(screen.width as f32, screen.height as f32),
         }
     }
 
-    pub(crate) fn to_uniform_data(&self) -> [f32; 13] {
-        [
-            self.position.x,
-            self.position.y,
-            self.position.z,
-            self.direction.x,
-            self.direction.y,
-            self.direction.z,
-            self.up.x,
-            self.up.y,
-            self.up.z,
-            self.fov,
-            self.far,
-            self.screen.x,
-            self.screen.y,
-        ]
+    pub(crate) fn projection(&self) -> CameraProjection {
+        self.projection
+    }
+
+    pub(crate) fn set_projection(&mut self, projection: CameraProjection) {
+        self.projection = projection;
+    }
+
+    /// Moves the camera to `eye` and points it at `target`. The `up` vector does not need to be
+    /// perpendicular to the view direction, but must not be parallel to it.
+    pub(crate) fn look_at(&mut self, eye: Vec3, target: Vec3, up: Vec3) {
+        self.position = eye;
+        self.direction = (target - eye).normalize();
+        self.up = up;
+    }
+
+    /// Moves the camera onto a sphere of the given radius around `target`, and points it at the
+    /// target. A yaw and pitch of zero place the camera on the -Z side of the target, looking along
+    /// +Z. Yaw turns the camera around the Y axis and pitch raises it above the target, both in
+    /// radians. The pitch should stay strictly between -π/2 and π/2, where the camera would be
+    /// looking straight along its up vector.
+    pub(crate) fn orbit(&mut self, target: Vec3, radius: f32, yaw: f32, pitch: f32) {
+        let (sin_yaw, cos_yaw) = yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = pitch.sin_cos();
+        let offset = Vec3::new(cos_pitch * sin_yaw, sin_pitch, -cos_pitch * cos_yaw);
+        self.look_at(target + offset * radius, target, Vec3::Y);
+    }
+
+    pub(crate) fn to_uniform_data(&self) -> CameraUniform {
+        let (projection, size) = match self.projection {
+            CameraProjection::Perspective { fov } => (0, fov),
+            CameraProjection::Orthographic { height } => (1, height),
+        };
+        CameraUniform {
+            position: self.position.to_array(),
+            size,
+            forward: self.direction.to_array(),
+            aspect: self.screen.x / self.screen.y,
+            up: self.up.to_array(),
+            near: self.near,
+            resolution: self.screen.to_array(),
+            far: self.far,
+            projection,
+        }
     }
 
     pub(crate) fn resize(&mut self, width: u32, height: u32) {
         self.screen = Vec2::new(width as f32, height as f32);
     }
+
+    /// Returns the pixel whose ray passes closest to the given point. This inverts `ray` by trying
+    /// every pixel, which is only fast enough for the small screens used by tests.
+    #[cfg(test)]
+    pub(crate) fn project(&self, point: Vec3) -> (u32, u32) {
+        let miss = |(x, y): (u32, u32)| {
+            let (origin, direction) = self.ray(Vec2::new(x as f32, y as f32));
+            (point - origin).cross(direction).length() / direction.length()
+        };
+        let (width, height) = (self.screen.x as u32, self.screen.y as u32);
+        (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .min_by(|a, b| miss(*a).total_cmp(&miss(*b)))
+            .expect("the screen has at least one pixel")
+    }
+
+    /// Returns the origin and direction of the ray cast through the given pixel, in the same way as
+    /// the ray generation in `compute.wgsl`.
+    #[cfg(test)]
+    fn ray(&self, pixel: Vec2) -> (Vec3, Vec3) {
+        let centered = (pixel - self.screen * 0.5) * Vec2::new(1.0, -1.0);
+        let horizontal = self.direction.cross(self.up).normalize();
+        let vertical = horizontal.cross(self.direction).normalize();
+        match self.projection {
+            CameraProjection::Perspective { fov } => {
+                let angles = fov * centered / self.screen.max_element();
+                let direction = Quat::from_axis_angle(horizontal, angles.y.to_radians())
+                    * (Quat::from_axis_angle(vertical, angles.x.to_radians()) * self.direction);
+                (self.position, direction)
+            }
+            CameraProjection::Orthographic { height } => {
+                let offset = height * centered / self.screen.y;
+                let origin = self.position + vertical * offset.y - horizontal * offset.x;
+                (origin, self.direction)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Camera, CameraProjection, CameraUniform};
+    use glam::{Vec2, Vec3};
+    use std::{
+        f32::consts::FRAC_PI_4,
+        mem::{offset_of, size_of},
+    };
+    use winit::dpi::PhysicalSize;
+
+    fn default_camera() -> Camera {
+        Camera::new(
+            Vec3::ZERO,
+            Vec3::Z,
+            Vec3::Y,
+            CameraProjection::Perspective { fov: 90.0 },
+            0.1,
+            100.0,
+            PhysicalSize::new(100, 100),
+        )
+    }
+
+    // Test that the uniform struct has the size and field offsets that `compute.wgsl` expects.
+    #[test]
+    fn test_uniform_layout() {
+        assert_eq!(size_of::<CameraUniform>(), 64);
+        assert_eq!(size_of::<CameraUniform>() % 16, 0);
+        assert_eq!(offset_of!(CameraUniform, position), 0);
+        assert_eq!(offset_of!(CameraUniform, size), 12);
+        assert_eq!(offset_of!(CameraUniform, forward), 16);
+        assert_eq!(offset_of!(CameraUniform, aspect), 28);
+        assert_eq!(offset_of!(CameraUniform, up), 32);
+        assert_eq!(offset_of!(CameraUniform, near), 44);
+        assert_eq!(offset_of!(CameraUniform, resolution), 48);
+        assert_eq!(offset_of!(CameraUniform, far), 56);
+        assert_eq!(offset_of!(CameraUniform, projection), 60);
+    }
+
+    // Test that resizing the camera updates both the resolution and the aspect ratio.
+    #[test]
+    fn test_resize_updates_uniform() {
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            Vec3::Z,
+            Vec3::Y,
+            CameraProjection::Perspective { fov: 90.0 },
+            0.1,
+            100.0,
+            PhysicalSize::new(100, 100),
+        );
+        assert_eq!(camera.to_uniform_data().aspect, 1.0);
+
+        camera.resize(200, 50);
+        let uniform = camera.to_uniform_data();
+        assert_eq!(uniform.resolution, [200.0, 50.0]);
+        assert_eq!(uniform.aspect, 4.0);
+        assert_eq!(uniform.forward, [0.0, 0.0, 1.0]);
+    }
+
+    // Test that orthographic rays through different pixels are parallel but start from different
+    // points, while perspective rays share an origin and diverge.
+    #[test]
+    fn test_orthographic_rays_parallel() {
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            Vec3::Z,
+            Vec3::Y,
+            CameraProjection::Orthographic { height: 4.0 },
+            0.1,
+            100.0,
+            PhysicalSize::new(100, 100),
+        );
+        let (first_origin, first_direction) = camera.ray(Vec2::new(10.0, 20.0));
+        let (second_origin, second_direction) = camera.ray(Vec2::new(90.0, 70.0));
+        assert!(first_direction.distance(second_direction) < 0.001);
+        assert!(first_origin.distance(second_origin) > 1.0);
+        assert_eq!(camera.to_uniform_data().projection, 1);
+
+        camera.set_projection(CameraProjection::Perspective { fov: 90.0 });
+        let (first_origin, first_direction) = camera.ray(Vec2::new(10.0, 20.0));
+        let (second_origin, second_direction) = camera.ray(Vec2::new(90.0, 70.0));
+        assert_eq!(first_origin, second_origin);
+        assert!(first_direction.distance(second_direction) > 0.1);
+    }
+
+    // Test that looking at a target points the uniform's forward vector from the eye to the target.
+    #[test]
+    fn test_look_at() {
+        let mut camera = default_camera();
+        let eye = Vec3::new(1.0, 2.0, 3.0);
+        let target = Vec3::new(-3.0, 2.0, 0.0);
+        camera.look_at(eye, target, Vec3::Y);
+        let uniform = camera.to_uniform_data();
+        assert_eq!(uniform.position, eye.to_array());
+        let forward = Vec3::from_array(uniform.forward);
+        assert!(forward.distance(Vec3::new(-0.8, 0.0, -0.6)) < 0.001);
+        assert!((forward.length() - 1.0).abs() < 0.001);
+    }
+
+    // Test that orbiting places the camera at the given radius from the target, facing it, and that
+    // yaw and pitch move the camera in the documented directions.
+    #[test]
+    fn test_orbit() {
+        let mut camera = default_camera();
+        let target = Vec3::new(1.0, 0.0, 1.0);
+        camera.orbit(target, 2.0, 0.0, 0.0);
+        assert!(camera.position.distance(Vec3::new(1.0, 0.0, -1.0)) < 0.001);
+        assert!(camera.direction.distance(Vec3::Z) < 0.001);
+
+        for (yaw, pitch) in [(1.0, 0.5), (-2.5, -1.2), (3.0, FRAC_PI_4)] {
+            camera.orbit(target, 2.0, yaw, pitch);
+            let uniform = camera.to_uniform_data();
+            let position = Vec3::from_array(uniform.position);
+            let forward = Vec3::from_array(uniform.forward);
+            assert!((position.distance(target) - 2.0).abs() < 0.001);
+            assert!(forward.distance((target - position).normalize()) < 0.001);
+            assert_eq!(position.y > target.y, pitch > 0.0);
+        }
+    }
 }
@@ -0,0 +1,309 @@
+use std::{error::Error, fmt};
+use wgpu::{
+    CreateSurfaceError, Features, Limits, RequestDeviceError, TextureFormat, TextureUsages,
+};
+use winit::error::OsError;
+
+/// The formats the compute pass can write its output in, along with their names in WGSL. Surface
+/// formats are matched against these by their non-sRGB variant, because the compute pass writes to
+/// a non-sRGB view of the surface texture.
+const STORAGE_FORMATS: [(TextureFormat, &str); 4] = [
+    (TextureFormat::Bgra8Unorm, "bgra8unorm"),
+    (TextureFormat::Rgba8Unorm, "rgba8unorm"),
+    (TextureFormat::Rgba16Float, "rgba16float"),
+    (TextureFormat::Rgba32Float, "rgba32float"),
+];
+
+/// The format of the surface texture when the surface lists no formats at all, such as when there
+/// is no surface. Bgra8Unorm (and the Srgb variant) is the only format guaranteed to be supported by
+/// all platforms.
+const FALLBACK_SURFACE_FORMAT: TextureFormat = TextureFormat::Bgra8UnormSrgb;
+
+/// The `GraphicsCapabilities` struct records which optional features of an adapter the renderer can
+/// make use of. It is probed from the adapter before the device is requested, so that the renderer
+/// can pick a fallback path for any feature that is missing rather than failing to start.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct GraphicsCapabilities {
+    /// Whether the adapter supports the `BGRA8UNORM_STORAGE` feature, which lets the compute pass
+    /// write directly to a `Bgra8Unorm` surface texture.
+    pub(crate) bgra8unorm_storage: bool,
+    /// The format of the surface texture, if the compute pass can write to it directly. Without
+    /// one, the compute pass writes to an `Rgba8Unorm` texture instead, which is then copied to the
+    /// surface by a render pass.
+    pub(crate) direct_format: Option<TextureFormat>,
+    /// The first format the surface supports, which the surface is configured with when the compute
+    /// pass cannot write to it directly.
+    pub(crate) preferred_format: Option<TextureFormat>,
+}
+
+/// The `GraphicsInitError` enum describes the reasons that creating a `Graphics` object may fail.
+#[derive(Debug)]
+pub(crate) enum GraphicsInitError {
+    /// The window could not be created.
+    Window(OsError),
+    /// The surface for the window could not be created.
+    Surface(CreateSurfaceError),
+    /// No adapter compatible with the surface was found.
+    NoAdapter,
+    /// The adapter cannot allocate buffers as large as the renderer requires.
+    BufferLimit { supported: u64, required: u64 },
+    /// The adapter refused to create a device.
+    Device(RequestDeviceError),
+}
+
+impl GraphicsCapabilities {
+    /// Probes the capabilities of an adapter from the features and limits it reports, for a voxel
+    /// storage buffer of the given size in bytes. Returns an error if the adapter lacks a capability
+    /// that has no fallback.
+    pub(crate) fn probe(
+        features: Features,
+        limits: &Limits,
+        voxel_store_size: u64,
+    ) -> Result<Self, GraphicsInitError> {
+        // The voxel storage buffer is a single storage buffer, with no fallback for smaller buffers
+        // yet
+        let supported = limits
+            .max_buffer_size
+            .min(limits.max_storage_buffer_binding_size as u64);
+        if supported < voxel_store_size {
+            return Err(GraphicsInitError::BufferLimit {
+                supported,
+                required: voxel_store_size,
+            });
+        }
+
+        Ok(Self {
+            bgra8unorm_storage: features.contains(Features::BGRA8UNORM_STORAGE),
+            direct_format: None,
+            preferred_format: None,
+        })
+    }
+
+    /// Returns these capabilities with the surface format chosen from the formats supported by the
+    /// surface, which are listed in order of preference, and the usages the surface supports. The
+    /// first format which the compute pass can write to is chosen, if there is one. No format is
+    /// chosen if the surface cannot be used as a storage texture at all, in which case the surface
+    /// is configured with its first format.
+    pub(crate) fn with_surface_formats(
+        mut self,
+        formats: &[TextureFormat],
+        usages: TextureUsages,
+    ) -> Self {
+        self.preferred_format = formats.first().copied();
+        if !usages.contains(TextureUsages::STORAGE_BINDING) {
+            self.direct_format = None;
+            return self;
+        }
+        let features = if self.bgra8unorm_storage {
+            Features::BGRA8UNORM_STORAGE
+        } else {
+            Features::empty()
+        };
+        self.direct_format = formats.iter().copied().find(|format| {
+            let view_format = format.remove_srgb_suffix();
+            STORAGE_FORMATS
+                .iter()
+                .any(|(storage, _)| *storage == view_format)
+                && view_format
+                    .guaranteed_format_features(features)
+                    .allowed_usages
+                    .contains(TextureUsages::STORAGE_BINDING)
+        });
+        self
+    }
+
+    /// Returns the features to request from the adapter, which are only those needed to write to
+    /// the chosen output format.
+    pub(crate) fn required_features(&self) -> Features {
+        if self.output_format() == TextureFormat::Bgra8Unorm {
+            Features::BGRA8UNORM_STORAGE
+        } else {
+            Features::empty()
+        }
+    }
+
+    /// Returns whether the compute pass writes directly to the surface texture.
+    pub(crate) fn writes_to_surface(&self) -> bool {
+        self.direct_format.is_some()
+    }
+
+    /// Returns the format to configure the surface with.
+    pub(crate) fn surface_format(&self) -> TextureFormat {
+        self.direct_format
+            .or(self.preferred_format)
+            .unwrap_or(FALLBACK_SURFACE_FORMAT)
+    }
+
+    /// Returns the format of the storage texture the compute pass writes to.
+    pub(crate) fn output_format(&self) -> TextureFormat {
+        self.direct_format
+            .map(|format| format.remove_srgb_suffix())
+            .unwrap_or(TextureFormat::Rgba8Unorm)
+    }
+
+    /// Returns the name of the output format as written in WGSL, to be substituted into the compute
+    /// shader.
+    pub(crate) fn output_format_wgsl(&self) -> &'static str {
+        let output_format = self.output_format();
+        STORAGE_FORMATS
+            .iter()
+            .find(|(format, _)| *format == output_format)
+            .map(|(_, name)| *name)
+            .expect("the output format is always one of the storage formats")
+    }
+}
+
+impl fmt::Display for GraphicsInitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Window(error) => write!(f, "failed to create the window: {error}"),
+            Self::Surface(error) => write!(f, "failed to create the surface: {error}"),
+            Self::NoAdapter => write!(f, "failed to find an appropriate adapter"),
+            Self::BufferLimit {
+                supported,
+                required,
+            } => write!(
+                f,
+                "the adapter supports buffers of up to {supported} bytes, but {required} are required"
+            ),
+            Self::Device(error) => write!(f, "failed to request a device: {error}"),
+        }
+    }
+}
+
+impl Error for GraphicsInitError {}
+
+impl From<OsError> for GraphicsInitError {
+    fn from(error: OsError) -> Self {
+        Self::Window(error)
+    }
+}
+
+impl From<CreateSurfaceError> for GraphicsInitError {
+    fn from(error: CreateSurfaceError) -> Self {
+        Self::Surface(error)
+    }
+}
+
+impl From<RequestDeviceError> for GraphicsInitError {
+    fn from(error: RequestDeviceError) -> Self {
+        Self::Device(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GraphicsCapabilities, GraphicsInitError};
+    use crate::graphics::GIGABYTE;
+    use wgpu::{Features, Limits, TextureFormat, TextureUsages};
+
+    // The usages of a surface which can be written to by the compute pass
+    const SURFACE_USAGES: TextureUsages =
+        TextureUsages::RENDER_ATTACHMENT.union(TextureUsages::STORAGE_BINDING);
+
+    fn large_limits() -> Limits {
+        Limits {
+            max_buffer_size: GIGABYTE as u64,
+            max_storage_buffer_binding_size: GIGABYTE as u32,
+            ..Default::default()
+        }
+    }
+
+    // Test that an adapter without `BGRA8UNORM_STORAGE` falls back to an `Rgba8Unorm` output rather
+    // than failing, when the surface only supports `Bgra8Unorm`.
+    #[test]
+    fn test_fallback_without_bgra_storage() {
+        let formats = [TextureFormat::Bgra8UnormSrgb, TextureFormat::Bgra8Unorm];
+        let capabilities =
+            GraphicsCapabilities::probe(Features::empty(), &large_limits(), GIGABYTE as u64)
+                .unwrap()
+                .with_surface_formats(&formats, SURFACE_USAGES);
+        assert!(!capabilities.writes_to_surface());
+        assert_eq!(capabilities.required_features(), Features::empty());
+        assert_eq!(capabilities.output_format(), TextureFormat::Rgba8Unorm);
+        assert_eq!(capabilities.surface_format(), TextureFormat::Bgra8UnormSrgb);
+
+        let capabilities = GraphicsCapabilities::probe(
+            Features::BGRA8UNORM_STORAGE,
+            &large_limits(),
+            GIGABYTE as u64,
+        )
+        .unwrap()
+        .with_surface_formats(&formats, SURFACE_USAGES);
+        assert_eq!(capabilities.surface_format(), TextureFormat::Bgra8UnormSrgb);
+        assert_eq!(capabilities.output_format(), TextureFormat::Bgra8Unorm);
+        assert_eq!(capabilities.output_format_wgsl(), "bgra8unorm");
+        assert_eq!(
+            capabilities.required_features(),
+            Features::BGRA8UNORM_STORAGE
+        );
+    }
+
+    // Test that the first surface format the compute pass can write to is chosen, skipping those it
+    // cannot.
+    #[test]
+    fn test_surface_format_selection() {
+        let capabilities =
+            GraphicsCapabilities::probe(Features::empty(), &large_limits(), GIGABYTE as u64)
+                .unwrap();
+        let formats = [
+            TextureFormat::Bgra8UnormSrgb,
+            TextureFormat::Rgb10a2Unorm,
+            TextureFormat::Rgba8UnormSrgb,
+            TextureFormat::Rgba16Float,
+        ];
+        let chosen = capabilities.with_surface_formats(&formats, SURFACE_USAGES);
+        assert_eq!(chosen.surface_format(), TextureFormat::Rgba8UnormSrgb);
+        assert_eq!(chosen.output_format_wgsl(), "rgba8unorm");
+        assert_eq!(chosen.required_features(), Features::empty());
+
+        let chosen = capabilities.with_surface_formats(&formats[1..2], SURFACE_USAGES);
+        assert!(!chosen.writes_to_surface());
+        let chosen =
+            capabilities.with_surface_formats(&[TextureFormat::Rgba16Float], SURFACE_USAGES);
+        assert_eq!(chosen.output_format_wgsl(), "rgba16float");
+    }
+
+    // Test that the fallback output is used when the surface cannot be a storage texture, even
+    // though the compute pass could write to its format, and that the surface is then configured
+    // with the first format it lists.
+    #[test]
+    fn test_fallback_without_storage_usage() {
+        let capabilities =
+            GraphicsCapabilities::probe(Features::empty(), &large_limits(), GIGABYTE as u64)
+                .unwrap()
+                .with_surface_formats(
+                    &[TextureFormat::Rgba8UnormSrgb],
+                    TextureUsages::RENDER_ATTACHMENT,
+                );
+        assert!(!capabilities.writes_to_surface());
+        assert_eq!(capabilities.output_format(), TextureFormat::Rgba8Unorm);
+        assert_eq!(capabilities.surface_format(), TextureFormat::Rgba8UnormSrgb);
+
+        // The formats offered by a WebGPU canvas, none of which are sRGB
+        let formats = [
+            TextureFormat::Bgra8Unorm,
+            TextureFormat::Rgba8Unorm,
+            TextureFormat::Rgba16Float,
+        ];
+        let capabilities =
+            capabilities.with_surface_formats(&formats, TextureUsages::RENDER_ATTACHMENT);
+        assert_eq!(capabilities.surface_format(), TextureFormat::Bgra8Unorm);
+
+        let capabilities = capabilities.with_surface_formats(&[], TextureUsages::RENDER_ATTACHMENT);
+        assert_eq!(capabilities.surface_format(), TextureFormat::Bgra8UnormSrgb);
+    }
+
+    // Test that an adapter with the default buffer limits is reported with an error when the voxel
+    // storage buffer is too large for it, but not when the buffer fits.
+    #[test]
+    fn test_buffer_limit_error() {
+        let result =
+            GraphicsCapabilities::probe(Features::all(), &Limits::default(), GIGABYTE as u64);
+        assert!(matches!(
+            result,
+            Err(GraphicsInitError::BufferLimit { required, .. }) if required == GIGABYTE as u64
+        ));
+        assert!(GraphicsCapabilities::probe(Features::all(), &Limits::default(), 1 << 20).is_ok());
+    }
+}
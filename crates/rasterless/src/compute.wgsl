@@ -1,52 +1,111 @@
+// Must match the layout of `CameraUniform` in `camera.rs`. The size is the field of view in degrees
+// for a perspective projection, or the view height for an orthographic projection.
 struct Camera {
     position: vec3f,
-    direction: vec3f,
+    size: f32,
+    forward: vec3f,
+    aspect: f32,
     up: vec3f,
-    fov: f32,
+    near: f32,
+    resolution: vec2f,
     far: f32,
-    screen: vec2f
+    projection: u32,
 };
 
 @group(0) @binding(0)
 var<uniform> camera: Camera;
+// The output format is substituted in by `Graphics::init`, depending on the adapter's capabilities
 @group(0) @binding(1)
-var output_texture: texture_storage_2d<bgra8unorm, write>;
+var output_texture: texture_storage_2d<{{OUTPUT_FORMAT}}, write>;
+// Must match the layout of `LightUniform` in `light.rs`
+struct Light {
+    position: vec3f,
+    intensity: f32,
+    color: vec3f,
+};
+
+// Must match the contents written by `light_buffer_contents` in `light.rs`
+struct Lights {
+    count: u32,
+    lights: array<Light>,
+};
+
+// Must match the layout of `PrimitiveUniform` in `primitive.rs`. The meaning of the position and
+// size depends on the kind of primitive, as listed there.
+struct Primitive {
+    position: vec3f,
+    kind: u32,
+    size: vec3f,
+};
+
+// Must match the contents written by `primitive_buffer_contents` in `primitive.rs`
+struct Primitives {
+    count: u32,
+    primitives: array<Primitive>,
+};
+
+// Must match the layout of `RenderSettingsUniform` in `settings.rs`
+struct RenderSettings {
+    shadow_softness: f32,
+    shadows_enabled: u32,
+    max_steps: u32,
+    hit_epsilon: f32,
+    max_distance: f32,
+};
+
 @group(0) @binding(2)
 var<storage> voxel_store: array<u32>;
+@group(0) @binding(3)
+var<storage> lights: Lights;
+@group(0) @binding(4)
+var<uniform> settings: RenderSettings;
+@group(0) @binding(5)
+var<storage> primitives: Primitives;
 
 @compute @workgroup_size(1)
 fn main(@builtin(global_invocation_id) id: vec3u) {
-    let centered = vec2f(id.xy) - camera.screen * 0.5;
-    let max_dimension = f32(max(camera.screen.x, camera.screen.y));
-    let angles = camera.fov * centered / max_dimension;
-
-    let horizontal_cross = cross(camera.direction, camera.up);
-    let vertical_cross = cross(horizontal_cross, camera.direction);
+    // Texture rows run downwards while the camera's up vector points upwards, so the vertical offset
+    // is negated to put row 0 at the top of the view
+    let centered = (vec2f(id.xy) - camera.resolution * 0.5) * vec2f(1.0, -1.0);
+    let max_dimension = f32(max(camera.resolution.x, camera.resolution.y));
 
-    let ray_direction = rotate(rotate(camera.direction, vertical_cross, angles.x), horizontal_cross, angles.y);
+    let horizontal_cross = normalize(cross(camera.forward, camera.up));
+    let vertical_cross = normalize(cross(horizontal_cross, camera.forward));
 
-    let center = vec3f(0.0, 0.0, 0.0);
-    let radius = 1.0;
-    let error_tolerance = 0.001;
+    var ray_origin = camera.position;
+    var ray_direction = camera.forward;
+    if camera.projection == 1u {
+        // Orthographic rays are parallel, starting from points spread across the view plane
+        let offset = camera.size * centered / camera.resolution.y;
+        ray_origin += vertical_cross * offset.y - horizontal_cross * offset.x;
+    } else {
+        let angles = camera.size * centered / max_dimension;
+        ray_direction = rotate(rotate(camera.forward, vertical_cross, angles.x), horizontal_cross, angles.y);
+    }
 
-    var current_position = camera.position;
-    var current_distance = distance(current_position, center) - radius;
-    var previous_distance = current_distance + 1.0;
+    var current_position = ray_origin + ray_direction * camera.near;
+    var current_distance = scene_distance(current_position);
+    var travelled = camera.near;
     var fill_color = vec4f(0.0, 0.0, 0.0, 1.0);
 
-    while previous_distance > current_distance {        
+    // Surfaces are opaque, so the march ends at the first hit. Otherwise it only ends once the ray
+    // runs out of steps or distance, because a ray moving away from one surface may still hit
+    // another behind it.
+    for (var step = 0u; step < settings.max_steps; step++) {
         current_position += ray_direction * current_distance;
-        previous_distance = current_distance;
-        current_distance = distance(current_position, center) - radius;
+        travelled += current_distance;
+        if travelled > settings.max_distance {
+            break;
+        }
+        current_distance = scene_distance(current_position);
 
         if fill_color.b < 1.0 {
             fill_color.b += 0.05;
             fill_color.g += 0.01;
         }
 
-        if current_distance < error_tolerance {
-            fill_color.g += 0.5;
-            fill_color.r += 0.2;
+        if current_distance < settings.hit_epsilon {
+            fill_color = vec4f(shade(current_position), 1.0);
             break;
         }
     }
@@ -54,6 +113,99 @@ fn main(@builtin(global_invocation_id) id: vec3u) {
     textureStore(output_texture, vec2u(id.x, id.y), fill_color);
 }
 
+// Returns the signed distance from the position to the nearest surface in the scene. An empty scene
+// is treated as having a surface at the camera's far distance.
+fn scene_distance(position: vec3f) -> f32 {
+    var nearest = camera.far;
+    for (var i = 0u; i < primitives.count; i++) {
+        nearest = min(nearest, primitive_distance(primitives.primitives[i], position));
+    }
+    return nearest;
+}
+
+// Returns the signed distance from the position to the surface of the primitive.
+fn primitive_distance(primitive: Primitive, position: vec3f) -> f32 {
+    switch primitive.kind {
+        // Sphere
+        case 0u: {
+            return distance(position, primitive.position) - primitive.size.x;
+        }
+        // Box
+        case 1u: {
+            let q = abs(position - primitive.position) - primitive.size;
+            return length(max(q, vec3f(0.0))) + min(max(q.x, max(q.y, q.z)), 0.0);
+        }
+        // Plane
+        case 2u: {
+            return dot(position, primitive.position) - primitive.size.x;
+        }
+        // Ellipsoid, using a bound which is exact on the surface
+        case 3u: {
+            let p = position - primitive.position;
+            let k0 = length(p / primitive.size);
+            let k1 = length(p / (primitive.size * primitive.size));
+            return k0 * (k0 - 1.0) / k1;
+        }
+        // Empty slots of the buffer, which are never hit
+        default: {
+            return camera.far;
+        }
+    }
+}
+
+// Estimates the surface normal at the position from the gradient of the scene's distance field.
+fn scene_normal(position: vec3f) -> vec3f {
+    let offset = vec2f(0.001, 0.0);
+    return normalize(vec3f(
+        scene_distance(position + offset.xyy) - scene_distance(position - offset.xyy),
+        scene_distance(position + offset.yxy) - scene_distance(position - offset.yxy),
+        scene_distance(position + offset.yyx) - scene_distance(position - offset.yyx)
+    ));
+}
+
+// Sums the diffuse contribution of every light to the surface at the position. Each light's
+// contribution falls off with the square of its distance, and is attenuated by shadows when they
+// are enabled.
+fn shade(position: vec3f) -> vec3f {
+    let normal = scene_normal(position);
+    var color = vec3f(0.0, 0.0, 0.0);
+    for (var i = 0u; i < lights.count; i++) {
+        let light = lights.lights[i];
+        let to_light = light.position - position;
+        let light_distance = length(to_light);
+        let light_direction = to_light / light_distance;
+        let diffuse = max(dot(normal, light_direction), 0.0);
+
+        var visibility = 1.0;
+        if settings.shadows_enabled != 0u && diffuse > 0.0 {
+            // Start slightly off the surface, so the shadow ray does not hit the surface it leaves
+            visibility = soft_shadow(position + normal * 0.01, light_direction, light_distance);
+        }
+
+        color += light.color * light.intensity * diffuse * visibility / (light_distance * light_distance);
+    }
+    return color;
+}
+
+// Marches from the origin towards a light, returning 0 if the light is occluded and 1 if it is
+// fully visible. Rays which pass close to an occluder without hitting it are partially shadowed,
+// which forms a penumbra whose width grows with the shadow softness.
+fn soft_shadow(origin: vec3f, direction: vec3f, max_distance: f32) -> f32 {
+    let error_tolerance = settings.hit_epsilon;
+    var visibility = 1.0;
+    var travelled = 0.0;
+    for (var step = 0; step < 64 && travelled < max_distance; step++) {
+        let current_distance = scene_distance(origin + direction * travelled);
+        if current_distance < error_tolerance {
+            return 0.0;
+        }
+        let penumbra = max(settings.shadow_softness * travelled, error_tolerance);
+        visibility = min(visibility, current_distance / penumbra);
+        travelled += current_distance;
+    }
+    return clamp(visibility, 0.0, 1.0);
+}
+
 fn rotate(vector: vec3f, axis: vec3f, angle: f32) -> vec3f {
     let q_rot = rotation_quaternion(axis, angle);
     let q_conj = conjugate_quaternion(q_rot);
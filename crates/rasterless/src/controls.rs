@@ -0,0 +1,99 @@
+use crate::{
+    camera::CameraProjection, graphics::Graphics, primitive::Primitive, settings::RenderSettings,
+};
+use glam::Vec3;
+use std::f32::consts::{FRAC_PI_4, FRAC_PI_8};
+use winit::keyboard::KeyCode;
+
+/// The `DemoControls` struct holds the keyboard bindings of the demo application, which exercise the
+/// scene and render settings of `Graphics` while it runs:
+///
+/// - S toggles shadows, and `[` and `]` make them harder or softer.
+/// - `-` and `=` halve and double the steps each ray may take.
+/// - P switches between perspective and orthographic projections.
+/// - Q and E orbit the camera around the origin.
+/// - The arrow keys move the first primitive, which must be the unit sphere at the origin.
+/// - N drops a small sphere onto the scene, and Backspace removes the last one dropped.
+pub(crate) struct DemoControls {
+    sphere_center: Vec3,
+    dropped_spheres: Vec<usize>,
+    camera_yaw: f32,
+}
+
+impl DemoControls {
+    pub(crate) fn new() -> Self {
+        Self {
+            sphere_center: Vec3::ZERO,
+            dropped_spheres: Vec::new(),
+            camera_yaw: 0.0,
+        }
+    }
+
+    /// Applies the binding of the given key to the graphics context. Keys without a binding are
+    /// ignored.
+    pub(crate) fn key_pressed(&mut self, graphics: &mut Graphics, code: KeyCode) {
+        let settings = graphics.render_settings();
+        match code {
+            KeyCode::KeyS => graphics.set_shadows_enabled(!settings.shadows_enabled),
+            KeyCode::BracketLeft => graphics.set_shadow_softness(settings.shadow_softness * 0.5),
+            // Hard shadows have zero softness, which doubling would never leave, so softening
+            // starts from a small floor
+            KeyCode::BracketRight => {
+                graphics.set_shadow_softness((settings.shadow_softness * 2.0).max(0.01))
+            }
+            KeyCode::Minus => graphics.set_render_settings(RenderSettings {
+                max_steps: settings.max_steps / 2,
+                ..settings
+            }),
+            KeyCode::Equal => graphics.set_render_settings(RenderSettings {
+                max_steps: settings.max_steps.saturating_mul(2),
+                ..settings
+            }),
+            KeyCode::KeyP => graphics.set_camera_projection(match graphics.camera_projection() {
+                CameraProjection::Perspective { .. } => {
+                    CameraProjection::Orthographic { height: 6.0 }
+                }
+                CameraProjection::Orthographic { .. } => {
+                    CameraProjection::Perspective { fov: 90.0 }
+                }
+            }),
+            // The orbit keeps the height and distance the camera starts at
+            KeyCode::KeyQ | KeyCode::KeyE => {
+                self.camera_yaw += match code {
+                    KeyCode::KeyQ => -FRAC_PI_8,
+                    _ => FRAC_PI_8,
+                };
+                graphics.orbit_camera(Vec3::ZERO, 18.0_f32.sqrt(), self.camera_yaw, FRAC_PI_4)
+            }
+            KeyCode::ArrowLeft | KeyCode::ArrowRight | KeyCode::ArrowUp | KeyCode::ArrowDown => {
+                self.sphere_center += match code {
+                    KeyCode::ArrowLeft => Vec3::new(-0.25, 0.0, 0.0),
+                    KeyCode::ArrowRight => Vec3::new(0.25, 0.0, 0.0),
+                    KeyCode::ArrowUp => Vec3::new(0.0, 0.0, 0.25),
+                    _ => Vec3::new(0.0, 0.0, -0.25),
+                };
+                graphics.update_primitive(
+                    0,
+                    Primitive::Sphere {
+                        center: self.sphere_center,
+                        radius: 1.0,
+                    },
+                )
+            }
+            KeyCode::KeyN => {
+                let offset = self.dropped_spheres.len() as f32;
+                let index = graphics.add_primitive(Primitive::Sphere {
+                    center: Vec3::new(offset * 0.5 - 2.0, -0.75, -1.5),
+                    radius: 0.25,
+                });
+                self.dropped_spheres.push(index);
+            }
+            KeyCode::Backspace => {
+                if let Some(index) = self.dropped_spheres.pop() {
+                    graphics.remove_primitive(index);
+                }
+            }
+            _ => (),
+        }
+    }
+}
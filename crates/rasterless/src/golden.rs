@@ -0,0 +1,280 @@
+// Golden-image tests for `compute.wgsl`. Each test renders a small scene with a headless `Graphics`
+// object and compares the frame against a PNG in the `goldens` directory, within a tolerance which
+// allows for small differences in floating point precision between adapters.
+//
+// A missing golden image fails the test, and setting the `UPDATE_GOLDENS` environment variable
+// rewrites all of them from the rendered frames. The headless renderer allocates a small voxel
+// storage buffer, so the tests run on software adapters too. They are only skipped on machines
+// without any adapter at all.
+
+use crate::{
+    capabilities::GraphicsInitError, graphics::Graphics, light::Light, primitive::Primitive,
+    settings::RenderSettings,
+};
+use glam::Vec3;
+use std::{env, fs::File, io::BufWriter, path::PathBuf};
+use winit::dpi::PhysicalSize;
+
+// The largest difference allowed between any channel of a rendered pixel and its golden pixel
+const TOLERANCE: u8 = 2;
+
+const SIZE: PhysicalSize<u32> = PhysicalSize::new(64, 48);
+
+// Creates a headless `Graphics` object. Returns `None` if there is no adapter to render with, so
+// that the calling test can be skipped, and panics if the adapter fails in any other way.
+fn headless() -> Option<Graphics> {
+    match pollster::block_on(Graphics::headless(SIZE)) {
+        Ok(graphics) => Some(graphics),
+        Err(GraphicsInitError::NoAdapter) => {
+            eprintln!("skipping golden-image test: no adapter is available");
+            None
+        }
+        Err(error) => panic!("failed to create a headless graphics object: {error}"),
+    }
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("goldens")
+        .join(format!("{name}.png"))
+}
+
+fn write_golden(path: &PathBuf, pixels: &[u8]) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(File::create(path).unwrap()),
+        SIZE.width,
+        SIZE.height,
+    );
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .unwrap()
+        .write_image_data(pixels)
+        .unwrap();
+}
+
+fn read_golden(path: &PathBuf) -> Vec<u8> {
+    let mut reader = png::Decoder::new(File::open(path).unwrap())
+        .read_info()
+        .unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!((info.width, info.height), (SIZE.width, SIZE.height));
+    assert_eq!(info.color_type, png::ColorType::Rgba);
+    pixels.truncate(info.buffer_size());
+    pixels
+}
+
+// Compares the frame against the named golden image, or writes the golden image instead if
+// `UPDATE_GOLDENS` is set.
+fn assert_matches_golden(name: &str, pixels: &[u8]) {
+    let path = golden_path(name);
+    if env::var_os("UPDATE_GOLDENS").is_some() {
+        write_golden(&path, pixels);
+        return;
+    }
+    assert!(
+        path.exists(),
+        "{} is missing; run with UPDATE_GOLDENS=1 to create it",
+        path.display()
+    );
+
+    let golden = read_golden(&path);
+    assert_eq!(golden.len(), pixels.len());
+    let mismatched = pixels
+        .chunks(4)
+        .zip(golden.chunks(4))
+        .position(|(pixel, golden)| {
+            pixel
+                .iter()
+                .zip(golden)
+                .any(|(a, b)| a.abs_diff(*b) > TOLERANCE)
+        });
+    if let Some(index) = mismatched {
+        let (x, y) = (index as u32 % SIZE.width, index as u32 / SIZE.width);
+        panic!(
+            "frame differs from {} at pixel ({x}, {y}); run with UPDATE_GOLDENS=1 if this is expected",
+            path.display()
+        );
+    }
+}
+
+fn pixel(pixels: &[u8], x: u32, y: u32) -> [u8; 4] {
+    let index = ((y * SIZE.width + x) * 4) as usize;
+    pixels[index..index + 4].try_into().unwrap()
+}
+
+// Test that a single lit sphere in front of the default camera renders as expected: lit in the
+// middle of the frame, and surrounded by the background in the corners.
+#[test]
+fn test_lit_sphere() {
+    let Some(mut graphics) = headless() else {
+        return;
+    };
+    graphics.set_lights(&[Light::new(Vec3::new(2.0, 4.0, -3.0), Vec3::ONE, 25.0)]);
+    graphics.set_primitives(&[Primitive::Sphere {
+        center: Vec3::ZERO,
+        radius: 1.0,
+    }]);
+
+    let pixels = graphics.capture().unwrap();
+    assert!(pixel(&pixels, SIZE.width / 2, SIZE.height / 2)[0] > 0);
+    for (x, y) in [(0, 0), (SIZE.width - 1, 0), (0, SIZE.height - 1)] {
+        assert_eq!(pixel(&pixels, x, y)[0], 0);
+    }
+
+    assert_matches_golden("lit_sphere", &pixels);
+}
+
+// Test that a scene of two ellipsoids under a single light renders as expected, from the default
+// camera.
+#[test]
+fn test_ellipsoids() {
+    let Some(mut graphics) = headless() else {
+        return;
+    };
+    graphics.set_lights(&[Light::new(Vec3::new(2.0, 4.0, -3.0), Vec3::ONE, 25.0)]);
+    graphics.set_primitives(&[
+        Primitive::Ellipsoid {
+            center: Vec3::new(-1.0, 0.0, 0.0),
+            radii: Vec3::new(0.6, 1.0, 0.6),
+        },
+        Primitive::Ellipsoid {
+            center: Vec3::new(1.0, -0.25, 0.5),
+            radii: Vec3::new(1.0, 0.5, 0.75),
+        },
+    ]);
+
+    let pixels = graphics.capture().unwrap();
+    assert_matches_golden("ellipsoids", &pixels);
+}
+
+// Test that limiting rays to a handful of steps still renders the sphere, since rays aimed at it
+// reach its surface quickly, while the background stays clear.
+#[test]
+fn test_few_max_steps() {
+    let Some(mut graphics) = headless() else {
+        return;
+    };
+    graphics.set_lights(&[Light::new(Vec3::new(2.0, 4.0, -3.0), Vec3::ONE, 25.0)]);
+    graphics.set_primitives(&[Primitive::Sphere {
+        center: Vec3::ZERO,
+        radius: 1.0,
+    }]);
+    let full = graphics.capture().unwrap();
+
+    graphics.set_render_settings(RenderSettings {
+        max_steps: 16,
+        ..graphics.render_settings()
+    });
+    let cheap = graphics.capture().unwrap();
+    let center = (SIZE.width / 2, SIZE.height / 2);
+    assert!(pixel(&cheap, center.0, center.1)[0] > 0);
+    assert!(
+        pixel(&cheap, center.0, center.1)[0].abs_diff(pixel(&full, center.0, center.1)[0])
+            <= TOLERANCE
+    );
+    assert_eq!(pixel(&cheap, 0, 0)[0], 0);
+}
+
+// Test that a box and a sphere side by side both appear, by sampling the pixels at their projected
+// centers, while the pixel between them shows the background. The background has no red in it,
+// unlike the white-lit primitives.
+#[test]
+fn test_box_and_sphere() {
+    let Some(mut graphics) = headless() else {
+        return;
+    };
+    graphics.set_lights(&[Light::new(Vec3::new(2.0, 4.0, -3.0), Vec3::ONE, 25.0)]);
+    let box_center = Vec3::new(-1.5, 0.0, 0.0);
+    let sphere_center = Vec3::new(1.5, 0.0, 0.0);
+    graphics.set_primitives(&[
+        Primitive::Box {
+            center: box_center,
+            half_extents: Vec3::splat(0.6),
+        },
+        Primitive::Sphere {
+            center: sphere_center,
+            radius: 0.75,
+        },
+    ]);
+
+    let pixels = graphics.capture().unwrap();
+    for center in [box_center, sphere_center] {
+        let (x, y) = graphics.project(center);
+        assert!(pixel(&pixels, x, y)[0] > 0);
+    }
+    let (x, y) = graphics.project(Vec3::ZERO);
+    assert_eq!(pixel(&pixels, x, y)[0], 0);
+}
+
+// Test that a small ellipsoid hanging below the light casts a shadow onto a wide ellipsoid beneath
+// it, by comparing the pixel where the shadow falls with the same pixel when shadows are disabled.
+#[test]
+fn test_ellipsoid_shadow() {
+    let Some(mut graphics) = headless() else {
+        return;
+    };
+    graphics.set_lights(&[Light::new(Vec3::new(0.8, 6.0, 0.8), Vec3::ONE, 25.0)]);
+    graphics.set_primitives(&[
+        Primitive::Ellipsoid {
+            center: Vec3::new(0.0, -0.5, 0.0),
+            radii: Vec3::new(2.5, 0.3, 2.5),
+        },
+        Primitive::Ellipsoid {
+            center: Vec3::new(0.8, 1.2, 0.8),
+            radii: Vec3::splat(0.4),
+        },
+    ]);
+    let (x, y) = graphics.project(Vec3::new(0.8, -0.2, 0.8));
+    let shadowed = graphics.capture().unwrap();
+
+    graphics.set_shadows_enabled(false);
+    let unshadowed = graphics.capture().unwrap();
+    assert!(pixel(&unshadowed, x, y)[0] > 0);
+    assert!(pixel(&shadowed, x, y)[0] < pixel(&unshadowed, x, y)[0] / 2);
+
+    assert_matches_golden("ellipsoid_shadow", &shadowed);
+}
+
+// Test that rays which graze a sphere on their way to a plane behind it still hit the plane. The
+// sphere rests on the plane, so it can only hide parts of the plane, and every pixel which shows
+// the background with the sphere must also show it without the sphere. Background pixels are told
+// apart from surfaces by their blue tint, since the white light shades surfaces grey.
+#[test]
+fn test_grazing_ray_reaches_plane() {
+    let Some(mut graphics) = headless() else {
+        return;
+    };
+    graphics.set_lights(&[Light::new(Vec3::new(2.0, 4.0, -3.0), Vec3::ONE, 25.0)]);
+    graphics.set_shadows_enabled(false);
+    let plane = Primitive::Plane {
+        normal: Vec3::Y,
+        distance: -1.0,
+    };
+    graphics.set_primitives(&[plane]);
+    let plane_only = graphics.capture().unwrap();
+
+    graphics.set_primitives(&[
+        plane,
+        Primitive::Sphere {
+            center: Vec3::ZERO,
+            radius: 1.0,
+        },
+    ]);
+    let with_sphere = graphics.capture().unwrap();
+
+    let is_background = |pixel: [u8; 4]| pixel[2] > pixel[0];
+    for y in 0..SIZE.height {
+        for x in 0..SIZE.width {
+            if is_background(pixel(&with_sphere, x, y)) {
+                assert!(
+                    is_background(pixel(&plane_only, x, y)),
+                    "the ray through pixel ({x}, {y}) stopped before reaching the plane"
+                );
+            }
+        }
+    }
+}
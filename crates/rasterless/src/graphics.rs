@@ -1,13 +1,26 @@
-use crate::camera::Camera;
-use bytemuck::cast_slice;
+use crate::{
+    camera::{Camera, CameraProjection},
+    capabilities::{GraphicsCapabilities, GraphicsInitError},
+    light::{light_buffer_contents, Light},
+    primitive::{Primitive, PrimitiveUniform},
+    settings::RenderSettings,
+    storage::{SceneBuffer, StorageUpdate},
+};
+use bytemuck::bytes_of;
 use glam::Vec3;
-use log::info;
+use log::{info, warn};
 use std::{error::Error, sync::Arc};
 use util::{BufferInitDescriptor, DeviceExt};
 use wgpu::*;
 use winit::{dpi::PhysicalSize, event_loop::ActiveEventLoop, window::Window};
 
-const GIGABYTE: usize = 1073741824;
+pub(crate) const GIGABYTE: usize = 1073741824;
+
+// The size of the voxel storage buffer of a headless `Graphics` object. Headless rendering is only
+// used by tests, which do not read any voxels, so the buffer is kept small enough for software
+// adapters to allocate.
+#[cfg(test)]
+const HEADLESS_VOXEL_STORE_SIZE: usize = 1048576;
 
 /// The `Graphics` object is a container for all the context and functionality related to rendering in
 /// the `rasterless` engine. Creating the `Graphics` object will grab the GPU device and set up the
@@ -17,32 +30,71 @@ const GIGABYTE: usize = 1073741824;
 /// volume hierarchy. The single compute pass handles all graphical effects, including primary visibility,
 /// lighting, and even post-processing.
 ///
-/// The only `wgpu` feature your GPU must support in order to create a `Graphics` object is the
-/// `BGRA8UNORM_STORAGE` feature, which should be available on all devices running DX12, Vulkan, and Metal;
-/// both on web and native.
+/// The `Graphics` object configures the surface with the first format it supports that the compute pass
+/// can write to directly. This is usually `Bgra8Unorm` with the `BGRA8UNORM_STORAGE` feature, which should
+/// be available on all devices running DX12, Vulkan, and Metal; both on web and native. Without such a
+/// format, the compute pass writes to a separate texture, which is copied to the window by an extra
+/// render pass.
 pub(crate) struct Graphics {
-    window: Arc<Window>,
+    window: Option<Arc<Window>>,
     camera: Camera,
     camera_uniform: Buffer,
+    settings: RenderSettings,
+    settings_uniform: Buffer,
+    lights: Buffer,
+    primitives: Buffer,
+    primitive_slots: SceneBuffer<PrimitiveUniform>,
     voxel_store: Buffer,
-    surface: Surface<'static>,
+    surface: Option<Surface<'static>>,
     device: Device,
     queue: Queue,
     config: SurfaceConfiguration,
     raymarch_bind_group_layout: BindGroupLayout,
     raymarch_pipeline: ComputePipeline,
+    fallback: Option<FallbackTarget>,
+}
+
+/// The `FallbackTarget` holds the texture that the compute pass writes to when it cannot write to the
+/// surface texture directly, along with the render pipeline that copies that texture to the surface
+/// texture.
+struct FallbackTarget {
+    texture: Texture,
+    bind_group_layout: BindGroupLayout,
+    pipeline: RenderPipeline,
+}
+
+/// The `SurfaceRecovery` enum describes how `Graphics::draw` recovers when it fails to get the
+/// current surface texture.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum SurfaceRecovery {
+    /// The surface no longer matches the window, so it is configured again and the frame is skipped.
+    Reconfigure,
+    /// The surface is temporarily unavailable, so the frame is skipped.
+    SkipFrame,
+    /// The surface cannot be recovered, so the error is returned.
+    Fatal,
+}
+
+impl SurfaceRecovery {
+    /// Returns how to recover from the given error.
+    pub(crate) fn for_error(error: &SurfaceError) -> Self {
+        match error {
+            SurfaceError::Lost | SurfaceError::Outdated => Self::Reconfigure,
+            SurfaceError::Timeout => Self::SkipFrame,
+            SurfaceError::OutOfMemory => Self::Fatal,
+        }
+    }
 }
 
 impl Graphics {
     /// Create a `Graphics` object. This will create a window, as well as initializing all needed GPU context
-    /// and handles rendering. Returns an `Error` result if window creation fails, if surface creation (the
-    /// texture that will be rendered to and displayed on the window) fails, or if searching for a valid
-    /// adapter/device (the GPU) fails.
+    /// and handles rendering. Returns a `GraphicsInitError` if window creation fails, if surface creation
+    /// (the texture that will be rendered to and displayed on the window) fails, if searching for a valid
+    /// adapter/device (the GPU) fails, or if the adapter cannot allocate the buffers the renderer needs.
     ///
-    /// The only `wgpu` feature your GPU must support in order to create a `Graphics` object is the
-    /// `BGRA8UNORM_STORAGE` feature, which should be available on all devices running DX12, Vulkan, and Metal;
-    /// both on web and native.
-    pub(crate) async fn init(event_loop: &ActiveEventLoop) -> Result<Self, Box<dyn Error>> {
+    /// If none of the surface's formats can be used as a storage texture, the compute pass writes to an
+    /// `Rgba8Unorm` texture instead, and an extra render pass copies it to the surface texture.
+    pub(crate) async fn init(event_loop: &ActiveEventLoop) -> Result<Self, GraphicsInitError> {
         // Create a new window
         let attributes = Window::default_attributes().with_title("Rasterless");
         let window = Arc::new(event_loop.create_window(attributes)?);
@@ -73,25 +125,75 @@ impl Graphics {
                 force_fallback_adapter: false,
             })
             .await
-            .ok_or("Failed to find an appropriate adapter")?;
+            .ok_or(GraphicsInitError::NoAdapter)?;
+
+        Self::from_adapter(
+            &adapter,
+            current_size,
+            GIGABYTE as u64,
+            Some((window, surface)),
+        )
+        .await
+    }
+
+    /// Create a `Graphics` object without a window, which renders at the given size. It cannot `draw`,
+    /// but its frames can be read back with `capture`, which is used to test the shaders. Returns a
+    /// `GraphicsInitError` if no adapter is available, for example on machines without a GPU. Its voxel
+    /// storage buffer is much smaller than that of a windowed `Graphics` object, so that software
+    /// adapters can create it.
+    #[cfg(test)]
+    pub(crate) async fn headless(size: PhysicalSize<u32>) -> Result<Self, GraphicsInitError> {
+        let adapter = Instance::default()
+            .request_adapter(&RequestAdapterOptions {
+                power_preference: PowerPreference::HighPerformance,
+                compatible_surface: None,
+                force_fallback_adapter: false,
+            })
+            .await
+            .ok_or(GraphicsInitError::NoAdapter)?;
+
+        Self::from_adapter(&adapter, size, HEADLESS_VOXEL_STORE_SIZE as u64, None).await
+    }
+
+    // Creates the device, buffers, and pipelines for the given adapter, with a voxel storage buffer of
+    // the given size in bytes. The window and its surface are optional, and without them the compute
+    // pass always writes to the fallback texture, so that its output can be read back.
+    async fn from_adapter(
+        adapter: &Adapter,
+        current_size: PhysicalSize<u32>,
+        voxel_store_size: u64,
+        target: Option<(Arc<Window>, Surface<'static>)>,
+    ) -> Result<Self, GraphicsInitError> {
+        // Without a surface there is nothing to write to directly, so the fallback output is used
+        let mut capabilities =
+            GraphicsCapabilities::probe(adapter.features(), &adapter.limits(), voxel_store_size)?;
+        if let Some((_, surface)) = &target {
+            let surface_capabilities = surface.get_capabilities(adapter);
+            capabilities = capabilities
+                .with_surface_formats(&surface_capabilities.formats, surface_capabilities.usages);
+            if !capabilities.writes_to_surface() {
+                info!(
+                    "The compute pass cannot write to the surface. Using the fallback output path."
+                );
+            }
+        }
 
         let (device, queue) = adapter
             .request_device(
                 &DeviceDescriptor {
                     label: None,
                     // The BGRA8UNORM_STORAGE feature should be available on all modern platforms
-                    // and graphics backends, including the web. It is required because the surface
-                    // texture we write to must be of the Bgra8Unorm format--the only format
-                    // guaranteed to be supported by all platforms. Compute shaders can only write
-                    // to storage textures, and and using a texture of this format as a storage
-                    // texture is not allowed without this feature.
-                    required_features: Features::BGRA8UNORM_STORAGE,
+                    // and graphics backends, including the web. It is requested when the chosen
+                    // surface format is Bgra8Unorm, which is the case on most platforms. Compute
+                    // shaders can only write to storage textures, and using a texture of this format
+                    // as a storage texture is not allowed without this feature.
+                    required_features: capabilities.required_features(),
                     required_limits: Limits {
-                        // We need to allocate buffers of at least 1GB in size--the primary example
-                        // of such a buffer being the voxel storage buffer used during raymarching.
-                        // Thus, we set specify a maximum buffer size of 1GB.
-                        max_buffer_size: GIGABYTE as u64,
-                        max_storage_buffer_binding_size: GIGABYTE as u32,
+                        // We need to allocate buffers as large as the voxel storage buffer used
+                        // during raymarching, which is 1GB for a window. Thus, we specify a
+                        // maximum buffer size of the voxel storage buffer's size.
+                        max_buffer_size: voxel_store_size,
+                        max_storage_buffer_binding_size: voxel_store_size as u32,
                         ..Default::default()
                     },
                 },
@@ -101,11 +203,14 @@ impl Graphics {
 
         let config = SurfaceConfiguration {
             // We use the surface texture as a storage texture so that we can write to it directly
-            // from the compute shader.
-            usage: TextureUsages::STORAGE_BINDING,
-            // Bgra8Unorm (and the Srgb variant) is the only guaranteed format that all platforms
-            // will support.
-            format: TextureFormat::Bgra8UnormSrgb,
+            // from the compute shader. Without a surface format the compute shader can write to, we
+            // instead copy to it with a render pass.
+            usage: if capabilities.writes_to_surface() {
+                TextureUsages::STORAGE_BINDING
+            } else {
+                TextureUsages::RENDER_ATTACHMENT
+            },
+            format: capabilities.surface_format(),
             width: current_size.width,
             height: current_size.height,
             // AutoVsync is supported everywhere because of fallbacks which allow it to gracefully
@@ -114,46 +219,83 @@ impl Graphics {
             // Only buffer 2 frames ahead.
             desired_maximum_frame_latency: 2,
             alpha_mode: CompositeAlphaMode::Auto,
-            // Note that only view format conversions that are guaranteed are the original format
-            // of the texture and the Srgb/Non-Srgb variant of the format.
-            view_formats: vec![TextureFormat::Bgra8Unorm, TextureFormat::Bgra8UnormSrgb],
+            // The surface texture is viewed without sRGB conversion, so that colors written by the
+            // compute pass are not converted twice. Note that only view format conversions that are
+            // guaranteed are the original format of the texture and the Srgb/Non-Srgb variant of
+            // the format.
+            view_formats: vec![capabilities.surface_format().remove_srgb_suffix()],
         };
-        surface.configure(&device, &config);
+        if let Some((_, surface)) = &target {
+            surface.configure(&device, &config);
+        }
 
         // Initialize buffers
         // ------------------
-        let camera_position = Vec3::new(0.0, 3.0, -3.0);
-        let camera = Camera::new(
-            camera_position,
-            (-camera_position).normalize(),
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            Vec3::Z,
             Vec3::Y,
-            90.0,
+            CameraProjection::Perspective { fov: 90.0 },
+            0.1,
             100.0,
             current_size,
         );
+        camera.look_at(Vec3::new(0.0, 3.0, -3.0), Vec3::ZERO, Vec3::Y);
 
         let camera_uniform = device.create_buffer_init(&BufferInitDescriptor {
             label: Some("Camera Uniform Buffer"),
-            contents: cast_slice(&camera.to_uniform_data()),
+            contents: bytes_of(&camera.to_uniform_data()),
             // Add the copy destionation usage so that we can send write_buffer commands for
             // when the camera object changes.
             usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
         });
 
+        let settings = RenderSettings::default();
+
+        let settings_uniform = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Render Settings Uniform Buffer"),
+            contents: bytes_of(&settings.to_uniform_data()),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        // The scene starts without any lights, until `set_lights` is called.
+        let lights = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Light Storage Buffer"),
+            contents: &light_buffer_contents(&[]),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        });
+
+        // The scene starts empty, until `set_primitives` or `add_primitive` is called.
+        let primitive_slots = SceneBuffer::new(PrimitiveUniform::EMPTY);
+        let primitives = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Primitive Storage Buffer"),
+            contents: &primitive_slots.contents(),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        });
+
         let voxel_store = device.create_buffer(&BufferDescriptor {
             label: Some("Voxel Storage Buffer"),
-            size: GIGABYTE as u64,
+            size: voxel_store_size,
             // We want a large buffer, which means we must initialize this buffer as a storage buffer,
-            // and we want to write to it from the CPU, which means we need the MAP_WRITE flag.
+            // and we want to write to it from the CPU, which means we need the COPY_DST flag. The
+            // buffer is not mapped, because a mapped buffer cannot be written to by the queue or
+            // used in a compute pass.
             usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
-            mapped_at_creation: true,
+            mapped_at_creation: false,
         });
 
         queue.write_buffer(&voxel_store, 0, &[0u8, 1u8, 2u8, 3u8]);
 
         // Create raymarching (compute pass) pipeline
         // ------------------------------------------
-        let raymarch_shader = device.create_shader_module(include_wgsl!("compute.wgsl"));
+        // The format of the output texture depends on the capabilities of the adapter, so it is
+        // substituted into the shader source before compiling.
+        let raymarch_source = include_str!("compute.wgsl")
+            .replace("{{OUTPUT_FORMAT}}", capabilities.output_format_wgsl());
+        let raymarch_shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Raymarch (Compute Pass) Shader"),
+            source: ShaderSource::Wgsl(raymarch_source.into()),
+        });
 
         let raymarch_bind_group_layout =
             device.create_bind_group_layout(&BindGroupLayoutDescriptor {
@@ -176,7 +318,7 @@ impl Graphics {
                             // Note that write-only is the only valid access pattern for storage
                             // textures on web.
                             access: StorageTextureAccess::WriteOnly,
-                            format: TextureFormat::Bgra8Unorm,
+                            format: capabilities.output_format(),
                             view_dimension: TextureViewDimension::D2,
                         },
                         count: None,
@@ -191,6 +333,36 @@ impl Graphics {
                         },
                         count: None,
                     },
+                    BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 5,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
                 ],
             });
 
@@ -208,15 +380,28 @@ impl Graphics {
             compilation_options: Default::default(),
         });
 
+        let fallback = if capabilities.writes_to_surface() {
+            None
+        } else {
+            Some(FallbackTarget::new(&device, &config))
+        };
+
         info!(
             "Rasterless graphics context initialized. Selected adapter: {:?}.",
             adapter.get_info()
         );
 
+        let (window, surface) = target.unzip();
+
         Ok(Self {
             window,
             camera,
             camera_uniform,
+            settings,
+            settings_uniform,
+            lights,
+            primitives,
+            primitive_slots,
             voxel_store,
             surface,
             device,
@@ -224,6 +409,7 @@ impl Graphics {
             config,
             raymarch_bind_group_layout,
             raymarch_pipeline,
+            fallback,
         })
     }
 
@@ -235,24 +421,198 @@ impl Graphics {
         let width = new_size.width.max(1);
         let height = new_size.height.max(1);
 
-        self.config.width = new_size.width;
-        self.config.height = new_size.height;
-        self.surface.configure(&self.device, &self.config);
+        self.config.width = width;
+        self.config.height = height;
+        if let Some(surface) = &self.surface {
+            surface.configure(&self.device, &self.config);
+        }
 
         self.camera.resize(width, height);
 
+        // The fallback output texture must match the size of the surface texture it is copied to
+        if let Some(fallback) = self.fallback.as_mut() {
+            fallback.texture = FallbackTarget::create_texture(&self.device, width, height);
+        }
+
         // Queue a write_buffer command, which will be executed on demand, just before the next compute pass.
         // This will update the camera uniform buffer with the new screen size.
         self.queue.write_buffer(
             &self.camera_uniform,
             0,
-            cast_slice(&self.camera.to_uniform_data()),
+            bytes_of(&self.camera.to_uniform_data()),
         );
 
         // On macOS the window needs to be redrawn manually after resizing. There's negligible drawbacks for
         // rendering an additional frame on other platforms, so this functionality has not been isolated to
         // macOS.
-        self.window.request_redraw();
+        self.request_redraw();
+    }
+
+    /// Replaces the lights in the scene with the given lights. The light storage buffer is only
+    /// reallocated when the new lights do not fit in the current buffer; otherwise the new lights
+    /// are written over the old ones. An empty slice of lights is valid, and leaves the scene unlit.
+    pub(crate) fn set_lights(&mut self, lights: &[Light]) {
+        let contents = light_buffer_contents(lights);
+        Self::write_storage(
+            &self.device,
+            &self.queue,
+            &mut self.lights,
+            &contents,
+            "Light Storage Buffer",
+        );
+    }
+
+    /// Replaces the primitives in the scene with the given primitives, which take the indices from
+    /// zero onwards. As with `set_lights`, the primitive storage buffer is only reallocated when the
+    /// new primitives do not fit in it. An empty slice of primitives is valid, and leaves nothing for
+    /// rays to hit.
+    pub(crate) fn set_primitives(&mut self, primitives: &[Primitive]) {
+        let uniforms: Vec<PrimitiveUniform> = primitives
+            .iter()
+            .map(|primitive| primitive.to_uniform_data())
+            .collect();
+        self.primitive_slots.set_all(&uniforms);
+        self.upload_primitives();
+    }
+
+    /// Adds a primitive to the scene, returning the index it can later be updated or removed by.
+    /// Indices of removed primitives are reused.
+    pub(crate) fn add_primitive(&mut self, primitive: Primitive) -> usize {
+        let index = self.primitive_slots.insert(primitive.to_uniform_data());
+        self.upload_primitives();
+        index
+    }
+
+    /// Replaces the primitive at the given index, writing only that primitive to the GPU.
+    ///
+    /// Panics if there is no primitive at the index.
+    pub(crate) fn update_primitive(&mut self, index: usize, primitive: Primitive) {
+        self.primitive_slots
+            .update(index, primitive.to_uniform_data());
+        self.upload_primitives();
+    }
+
+    /// Removes the primitive at the given index from the scene.
+    ///
+    /// Panics if there is no primitive at the index.
+    pub(crate) fn remove_primitive(&mut self, index: usize) {
+        self.primitive_slots.remove(index);
+        self.upload_primitives();
+    }
+
+    // Applies the changes made to the primitive slots to the primitive storage buffer.
+    fn upload_primitives(&mut self) {
+        for update in self.primitive_slots.take_updates() {
+            match update {
+                StorageUpdate::Reallocate(contents) => {
+                    self.primitives = self.device.create_buffer_init(&BufferInitDescriptor {
+                        label: Some("Primitive Storage Buffer"),
+                        contents: &contents,
+                        usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+                    });
+                }
+                StorageUpdate::Write { offset, contents } => {
+                    self.queue.write_buffer(&self.primitives, offset, &contents);
+                }
+            }
+        }
+        self.request_redraw();
+    }
+
+    // Writes the contents to the storage buffer, replacing the buffer with a new one if the contents
+    // do not fit.
+    fn write_storage(
+        device: &Device,
+        queue: &Queue,
+        buffer: &mut Buffer,
+        contents: &[u8],
+        label: &str,
+    ) {
+        if contents.len() as u64 > buffer.size() {
+            *buffer = device.create_buffer_init(&BufferInitDescriptor {
+                label: Some(label),
+                contents,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+            });
+        } else {
+            queue.write_buffer(buffer, 0, contents);
+        }
+    }
+
+    // Requests a redraw of the window, if there is one.
+    fn request_redraw(&self) {
+        if let Some(window) = &self.window {
+            window.request_redraw();
+        }
+    }
+
+    /// Returns the projection of the camera.
+    pub(crate) fn camera_projection(&self) -> CameraProjection {
+        self.camera.projection()
+    }
+
+    /// Sets the projection of the camera, and requests a redraw so that the change is displayed.
+    pub(crate) fn set_camera_projection(&mut self, projection: CameraProjection) {
+        self.camera.set_projection(projection);
+        self.queue.write_buffer(
+            &self.camera_uniform,
+            0,
+            bytes_of(&self.camera.to_uniform_data()),
+        );
+        self.request_redraw();
+    }
+
+    /// Moves the camera around a target, as described by `Camera::orbit`, and requests a redraw so
+    /// that the change is displayed.
+    pub(crate) fn orbit_camera(&mut self, target: Vec3, radius: f32, yaw: f32, pitch: f32) {
+        self.camera.orbit(target, radius, yaw, pitch);
+        self.queue.write_buffer(
+            &self.camera_uniform,
+            0,
+            bytes_of(&self.camera.to_uniform_data()),
+        );
+        self.request_redraw();
+    }
+
+    /// Returns the current settings of the raymarching pass.
+    pub(crate) fn render_settings(&self) -> RenderSettings {
+        self.settings
+    }
+
+    /// Sets whether shadows are drawn. Disabling shadows skips marching rays from each surface
+    /// towards each light, which improves performance.
+    pub(crate) fn set_shadows_enabled(&mut self, enabled: bool) {
+        self.settings.shadows_enabled = enabled;
+        self.write_settings();
+    }
+
+    /// Sets the width of shadow penumbrae, relative to the distance from the occluder to the shaded
+    /// surface. Zero gives hard shadows, and negative values are clamped to zero.
+    pub(crate) fn set_shadow_softness(&mut self, softness: f32) {
+        self.settings.shadow_softness = softness.max(0.0);
+        self.write_settings();
+    }
+
+    /// Replaces all of the settings of the raymarching pass at once. Negative shadow softness is
+    /// clamped to zero, and rays always take at least one step.
+    pub(crate) fn set_render_settings(&mut self, settings: RenderSettings) {
+        self.settings = RenderSettings {
+            shadow_softness: settings.shadow_softness.max(0.0),
+            max_steps: settings.max_steps.max(1),
+            ..settings
+        };
+        self.write_settings();
+    }
+
+    // Queues a write of the render settings to their uniform buffer, and requests a redraw so that
+    // the change is displayed.
+    fn write_settings(&self) {
+        self.queue.write_buffer(
+            &self.settings_uniform,
+            0,
+            bytes_of(&self.settings.to_uniform_data()),
+        );
+        self.request_redraw();
     }
 
     /// The `draw` method will submit a new raymarching compute pass to the GPU, render a new frame, and
@@ -261,19 +621,149 @@ impl Graphics {
     /// effects, including primary visibility, lighting, and even post-processing.
     ///
     /// Note that the compute pass writes directly to the surface texture, which is what will eventually
-    /// be displayed on the window. It does not make a separate texture or copy any textures.
+    /// be displayed on the window. It does not make a separate texture or copy any textures, unless the
+    /// surface texture cannot be used as a storage texture, in which case the compute pass output is
+    /// copied to the surface texture by a render pass.
     ///
-    /// This method will return an `Error` result if it cannot get the current surface texture for any
-    /// reason.
+    /// If the surface is lost or outdated, which is common after a resize or when the device wakes
+    /// from sleep, the surface is configured again and the frame is skipped. If getting the surface
+    /// texture times out, the frame is also skipped. In both cases another redraw is requested.
+    ///
+    /// This method will return an `Error` result if the surface is out of memory, or if the `Graphics`
+    /// object is headless.
     pub(crate) fn draw(&self) -> Result<(), Box<dyn Error>> {
+        let surface = self
+            .surface
+            .as_ref()
+            .ok_or("cannot draw without a window surface")?;
+
+        let frame = match surface.get_current_texture() {
+            Ok(frame) => frame,
+            Err(error) => {
+                match SurfaceRecovery::for_error(&error) {
+                    SurfaceRecovery::Reconfigure => {
+                        warn!("Surface texture unavailable ({error}). Reconfiguring the surface.");
+                        surface.configure(&self.device, &self.config);
+                    }
+                    SurfaceRecovery::SkipFrame => {
+                        warn!("Surface texture unavailable ({error}). Skipping the frame.");
+                    }
+                    SurfaceRecovery::Fatal => return Err(error.into()),
+                }
+                self.request_redraw();
+                return Ok(());
+            }
+        };
+
         let mut encoder = self
             .device
             .create_command_encoder(&CommandEncoderDescriptor {
                 label: Some("Main Command Encoder"),
             });
 
-        let frame = self.surface.get_current_texture()?;
+        let frame_view = frame.texture.create_view(&TextureViewDescriptor {
+            label: Some("Raymarch (Compute Pass) Surface Texture View"),
+            format: Some(self.config.format.remove_srgb_suffix()),
+            dimension: Some(TextureViewDimension::D2),
+            ..Default::default()
+        });
+
+        // The compute pass writes to the fallback texture if there is one, or to the surface texture
+        let fallback_view = self.fallback.as_ref().map(|fallback| {
+            fallback
+                .texture
+                .create_view(&TextureViewDescriptor::default())
+        });
+        let output_view = fallback_view.as_ref().unwrap_or(&frame_view);
+
+        self.encode_raymarch(&mut encoder, output_view);
+
+        if let Some(fallback) = &self.fallback {
+            fallback.copy_to_surface(&self.device, &mut encoder, output_view, &frame_view);
+        }
+
+        self.queue.submit(Some(encoder.finish()));
+        frame.present();
+        Ok(())
+    }
+
+    /// Renders a frame and reads it back from the GPU, returning its pixels as tightly packed rows of
+    /// `Rgba8Unorm` texels, from the top row down. This blocks until the GPU has finished the frame.
+    /// Only headless `Graphics` objects can be captured, because only they are guaranteed to render to
+    /// the fallback texture.
+    #[cfg(test)]
+    pub(crate) fn capture(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let fallback = self
+            .fallback
+            .as_ref()
+            .ok_or("only headless graphics can be captured")?;
+        let (width, height) = (self.config.width, self.config.height);
+
+        // Rows copied out of a texture must be padded to a multiple of 256 bytes
+        let row_bytes = width * 4;
+        let padded_row_bytes =
+            row_bytes.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT;
+        let readback = self.device.create_buffer(&BufferDescriptor {
+            label: Some("Capture Readback Buffer"),
+            size: (padded_row_bytes * height) as u64,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Capture Command Encoder"),
+            });
+        let output_view = fallback
+            .texture
+            .create_view(&TextureViewDescriptor::default());
+        self.encode_raymarch(&mut encoder, &output_view);
+        encoder.copy_texture_to_buffer(
+            fallback.texture.as_image_copy(),
+            ImageCopyBuffer {
+                buffer: &readback,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_bytes),
+                    rows_per_image: Some(height),
+                },
+            },
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.queue.submit(Some(encoder.finish()));
+
+        // Wait for the frame to finish, then strip the padding from each row
+        let slice = readback.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(Maintain::Wait);
+        receiver.recv()??;
+
+        let padded = slice.get_mapped_range();
+        let pixels = padded
+            .chunks(padded_row_bytes as usize)
+            .flat_map(|row| &row[..row_bytes as usize])
+            .copied()
+            .collect();
+        Ok(pixels)
+    }
+
+    /// Returns the pixel of a captured frame in which the given point appears, as found by
+    /// `Camera::project`.
+    #[cfg(test)]
+    pub(crate) fn project(&self, point: Vec3) -> (u32, u32) {
+        self.camera.project(point)
+    }
 
+    // Records the raymarching compute pass, writing to the given output texture view.
+    fn encode_raymarch(&self, encoder: &mut CommandEncoder, output_view: &TextureView) {
         // We construct a new binding every frame because our reference to the surface texture is only
         // valid for a single frame. It should not be a costly operation to construct the bind group--
         // its size is small and construction is simple.
@@ -287,40 +777,192 @@ impl Graphics {
                 },
                 BindGroupEntry {
                     binding: 1,
-                    resource: BindingResource::TextureView(&frame.texture.create_view(
-                        &TextureViewDescriptor {
-                            label: Some("Raymarch (Compute Pass) Surface Texture View"),
-                            format: Some(TextureFormat::Bgra8Unorm),
-                            dimension: Some(TextureViewDimension::D2),
-                            ..Default::default()
-                        },
-                    )),
+                    resource: BindingResource::TextureView(output_view),
                 },
                 BindGroupEntry {
                     binding: 2,
                     resource: self.voxel_store.as_entire_binding(),
                 },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: self.lights.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 4,
+                    resource: self.settings_uniform.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 5,
+                    resource: self.primitives.as_entire_binding(),
+                },
             ],
         });
 
-        // Open a scoped block so that all values we allocate in it are dropped at the end of the block.
-        // We drop the compute pass because it borrows and continues to borrow the encoder after we
-        // call `begin_compute_pass`.
-        {
-            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
-                label: Some("Raymarching Compute Pass"),
-                timestamp_writes: None,
-            });
-            compute_pass.set_bind_group(0, &raymarch_bind_group, &[]);
-            compute_pass.set_pipeline(&self.raymarch_pipeline);
+        let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("Raymarching Compute Pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_bind_group(0, &raymarch_bind_group, &[]);
+        compute_pass.set_pipeline(&self.raymarch_pipeline);
+
+        // TODO: Workgroup sizes should be tested. Currently a workgroup is dispatched for every pixel,
+        // but it may be faster to dispatch workgroups of larger sizes.
+        compute_pass.dispatch_workgroups(self.config.width, self.config.height, 1);
+    }
+}
 
-            // TODO: Workgroup sizes should be tested. Currently a workgroup is dispatched for every pixel,
-            // but it may be faster to dispatch workgroups of larger sizes.
-            compute_pass.dispatch_workgroups(self.config.width, self.config.height, 1);
+impl FallbackTarget {
+    /// Creates the fallback output texture, sized to the surface, and the render pipeline which
+    /// copies it to the surface texture.
+    fn new(device: &Device, config: &SurfaceConfiguration) -> Self {
+        let texture = Self::create_texture(device, config.width, config.height);
+
+        let shader = device.create_shader_module(include_wgsl!("blit.wgsl"));
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Blit (Render Pass) Bind Group Layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Float { filterable: false },
+                    view_dimension: TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Blit (Render Pass) Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Blit (Render Pass) Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: "vertex",
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: "fragment",
+                compilation_options: Default::default(),
+                // The surface texture is viewed without sRGB conversion, the same as when the
+                // compute pass writes to it directly, so that colors are not converted twice.
+                targets: &[Some(ColorTargetState {
+                    format: config.format.remove_srgb_suffix(),
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
+
+        Self {
+            texture,
+            bind_group_layout,
+            pipeline,
         }
+    }
 
-        self.queue.submit(Some(encoder.finish()));
-        frame.present();
-        Ok(())
+    /// Creates a texture of the given size which the compute pass can write to, and the blit pass
+    /// can read from.
+    fn create_texture(device: &Device, width: u32, height: u32) -> Texture {
+        device.create_texture(&TextureDescriptor {
+            label: Some("Fallback Output Texture"),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba8Unorm,
+            // The copy source usage lets headless graphics read frames back for testing
+            usage: TextureUsages::STORAGE_BINDING
+                | TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        })
+    }
+
+    /// Records a render pass which copies the fallback output texture to the surface texture.
+    fn copy_to_surface(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        output_view: &TextureView,
+        frame_view: &TextureView,
+    ) {
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Blit (Render Pass) Bind Group"),
+            layout: &self.bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::TextureView(output_view),
+            }],
+        });
+
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Blit Render Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: frame_view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::BLACK),
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Graphics, SurfaceRecovery};
+    use crate::capabilities::GraphicsInitError;
+    use wgpu::SurfaceError;
+    use winit::dpi::PhysicalSize;
+
+    // Test that each surface error maps to the expected recovery, so that only running out of memory
+    // is reported as an error.
+    #[test]
+    fn test_surface_recovery() {
+        let recovery = SurfaceRecovery::for_error;
+        assert_eq!(recovery(&SurfaceError::Lost), SurfaceRecovery::Reconfigure);
+        assert_eq!(
+            recovery(&SurfaceError::Outdated),
+            SurfaceRecovery::Reconfigure
+        );
+        assert_eq!(recovery(&SurfaceError::Timeout), SurfaceRecovery::SkipFrame);
+        assert_eq!(recovery(&SurfaceError::OutOfMemory), SurfaceRecovery::Fatal);
+    }
+
+    // Test that resizing to zero, as happens when the window is minimized, configures a frame of
+    // one pixel rather than an empty one, matching the fallback texture the frame is rendered to.
+    #[test]
+    fn test_resize_to_zero() {
+        let mut graphics = match pollster::block_on(Graphics::headless(PhysicalSize::new(8, 8))) {
+            Ok(graphics) => graphics,
+            Err(GraphicsInitError::NoAdapter) => return,
+            Err(error) => panic!("failed to create a headless graphics object: {error}"),
+        };
+        graphics.resize(PhysicalSize::new(0, 0));
+        assert_eq!(graphics.capture().unwrap().len(), 4);
     }
 }
@@ -1,16 +1,32 @@
 use bevy::prelude::*;
 
+mod raymarch;
+
+pub use raymarch::RaymarchLabel;
+
+/// The `RasterlessRenderPlugin` adds a raymarch pass to the render graph of every 3D camera, which
+/// replaces the output of the main pass with the scene marched by a compute shader. The scene is
+/// made up of the `Ellipsoid` components in the world.
+///
+/// The raymarched image is drawn into the view target with whatever MSAA sample count the app
+/// uses, but each pixel is marched once, so multisampling only costs memory. Apps which draw
+/// nothing else should insert `Msaa::Off`.
 pub struct RasterlessRenderPlugin;
 impl Plugin for RasterlessRenderPlugin {
     fn build(&self, app: &mut App) {
+        raymarch::build_raymarch(app);
         info!("RasterlessRenderPlugin loaded!");
     }
     fn finish(&self, app: &mut App) {
+        raymarch::finish_raymarch(app);
         info!("RasterlessRenderPlugin initialization complete!");
     }
 }
 
+/// An `Ellipsoid` is marched as a sphere of the given radius, centered on the entity's transform.
+/// Only the first ellipsoid in the world is rendered so far.
 //#[derive(AsBindGroup, Component, Clone)]
+#[derive(Component, Clone, Copy, Debug)]
 pub struct Ellipsoid {
     //#[texture(0)]
     //#[sampler(1)]
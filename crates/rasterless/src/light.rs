@@ -0,0 +1,76 @@
+use crate::storage::counted_array_contents;
+use bytemuck::{Pod, Zeroable};
+use glam::Vec3;
+
+/// A `Light` is a point light source in the scene. Its contribution to a surface falls off with the
+/// square of the distance from its position.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Light {
+    pub(crate) position: Vec3,
+    pub(crate) color: Vec3,
+    pub(crate) intensity: f32,
+}
+
+/// The `LightUniform` struct is the data uploaded to the light storage buffer for each light. Its
+/// layout must match the `Light` struct in `compute.wgsl`: the position at offset 0, the intensity
+/// at offset 12, the color at offset 16, and padding up to a total of 32 bytes.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub(crate) struct LightUniform {
+    position: [f32; 3],
+    intensity: f32,
+    color: [f32; 3],
+    _padding: f32,
+}
+
+impl Light {
+    pub(crate) fn new(position: Vec3, color: Vec3, intensity: f32) -> Self {
+        Self {
+            position,
+            color,
+            intensity,
+        }
+    }
+
+    pub(crate) fn to_uniform_data(self) -> LightUniform {
+        LightUniform {
+            position: self.position.to_array(),
+            intensity: self.intensity,
+            color: self.color.to_array(),
+            _padding: 0.0,
+        }
+    }
+}
+
+/// Returns the contents of the light storage buffer for the given lights, matching the `Lights`
+/// struct in `compute.wgsl`. The buffer starts with a header holding the number of lights, followed
+/// by a `LightUniform` for each light.
+pub(crate) fn light_buffer_contents(lights: &[Light]) -> Vec<u8> {
+    let uniforms: Vec<LightUniform> = lights.iter().map(|light| light.to_uniform_data()).collect();
+    counted_array_contents(&uniforms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{light_buffer_contents, Light, LightUniform};
+    use glam::Vec3;
+    use std::mem::size_of;
+
+    // Test that the buffer holds a header followed by one entry per light.
+    #[test]
+    fn test_buffer_sized_to_lights() {
+        let light = Light::new(Vec3::Y, Vec3::ONE, 1.0);
+        let contents = light_buffer_contents(&[light, light, light]);
+        assert_eq!(size_of::<LightUniform>(), 32);
+        assert_eq!(contents.len(), 16 + 3 * 32);
+        assert_eq!(contents[..4], 3u32.to_le_bytes());
+    }
+
+    // Test that zero lights still produce a buffer which can be bound, reporting zero lights.
+    #[test]
+    fn test_buffer_without_lights() {
+        let contents = light_buffer_contents(&[]);
+        assert_eq!(contents.len(), 16 + 32);
+        assert_eq!(contents[..4], 0u32.to_le_bytes());
+    }
+}
@@ -4,7 +4,15 @@ use winit::event_loop::{ControlFlow, EventLoop};
 
 mod app;
 mod camera;
+mod capabilities;
+mod controls;
+#[cfg(test)]
+mod golden;
 mod graphics;
+mod light;
+mod primitive;
+mod settings;
+mod storage;
 
 use app::AppState;
 
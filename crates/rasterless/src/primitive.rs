@@ -0,0 +1,115 @@
+use bytemuck::{Pod, Zeroable};
+use glam::Vec3;
+
+/// A `Primitive` is a shape in the scene which the raymarcher can find the signed distance to. The
+/// scene is the union of all its primitives.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Primitive {
+    /// A sphere with the given center and radius.
+    Sphere { center: Vec3, radius: f32 },
+    /// An axis-aligned box with the given center, extending by `half_extents` along each axis.
+    Box { center: Vec3, half_extents: Vec3 },
+    /// An infinite plane containing the points `p` where `dot(p, normal) == distance`. The normal
+    /// must be normalized.
+    Plane { normal: Vec3, distance: f32 },
+    /// An axis-aligned ellipsoid with the given center and radius along each axis.
+    Ellipsoid { center: Vec3, radii: Vec3 },
+}
+
+/// The `PrimitiveUniform` struct is the data uploaded to the primitive storage buffer for each
+/// primitive. Its layout must match the `Primitive` struct in `compute.wgsl`: a position at offset
+/// 0, the kind tag at offset 12, a size at offset 16, and padding up to a total of 32 bytes.
+///
+/// The meaning of the position and size depends on the kind of primitive:
+///
+/// | Kind | Primitive   | `position` | `size`                |
+/// |------|-------------|------------|-----------------------|
+/// | 0    | `Sphere`    | center     | radius in `x`         |
+/// | 1    | `Box`       | center     | half extents          |
+/// | 2    | `Plane`     | normal     | distance in `x`       |
+/// | 3    | `Ellipsoid` | center     | radius along each axis |
+///
+/// Slots of the buffer which do not hold a primitive are filled with `PrimitiveUniform::EMPTY`,
+/// whose kind is not one of the above, so that rays never hit it.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub(crate) struct PrimitiveUniform {
+    position: [f32; 3],
+    kind: u32,
+    size: [f32; 3],
+    _padding: f32,
+}
+
+impl PrimitiveUniform {
+    /// The uniform data for an empty slot of the primitive storage buffer.
+    pub(crate) const EMPTY: Self = Self {
+        position: [0.0; 3],
+        kind: u32::MAX,
+        size: [0.0; 3],
+        _padding: 0.0,
+    };
+}
+
+impl Primitive {
+    pub(crate) fn to_uniform_data(self) -> PrimitiveUniform {
+        let (kind, position, size) = match self {
+            Self::Sphere { center, radius } => (0, center, Vec3::new(radius, 0.0, 0.0)),
+            Self::Box {
+                center,
+                half_extents,
+            } => (1, center, half_extents),
+            Self::Plane { normal, distance } => (2, normal, Vec3::new(distance, 0.0, 0.0)),
+            Self::Ellipsoid { center, radii } => (3, center, radii),
+        };
+        PrimitiveUniform {
+            position: position.to_array(),
+            kind,
+            size: size.to_array(),
+            _padding: 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Primitive, PrimitiveUniform};
+    use crate::storage::SceneBuffer;
+    use bytemuck::bytes_of;
+    use glam::Vec3;
+    use std::mem::size_of;
+
+    // Test that each kind of primitive is tagged and packed as `compute.wgsl` expects, following the
+    // count header.
+    #[test]
+    fn test_primitives_packed_with_tags() {
+        let sphere = Primitive::Sphere {
+            center: Vec3::ZERO,
+            radius: 1.0,
+        };
+        let cube = Primitive::Box {
+            center: Vec3::X,
+            half_extents: Vec3::splat(0.5),
+        };
+        let plane = Primitive::Plane {
+            normal: Vec3::Y,
+            distance: -1.0,
+        };
+        let mut buffer = SceneBuffer::new(PrimitiveUniform::EMPTY);
+        buffer.set_all(&[sphere, cube, plane].map(Primitive::to_uniform_data));
+        let contents = buffer.contents();
+        assert_eq!(size_of::<PrimitiveUniform>(), 32);
+        assert_eq!(contents.len(), 16 + 3 * 32);
+        assert_eq!(contents[..4], 3u32.to_le_bytes());
+
+        // The kind tag follows the position in each entry
+        for (index, kind) in [0u32, 1, 2].into_iter().enumerate() {
+            let offset = 16 + index * 32 + 12;
+            assert_eq!(contents[offset..offset + 4], kind.to_le_bytes());
+        }
+        assert_eq!(
+            contents[48..80],
+            *bytes_of(&cube.to_uniform_data()),
+            "the box should be packed second"
+        );
+    }
+}
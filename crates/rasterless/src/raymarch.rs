@@ -0,0 +1,394 @@
+use crate::Ellipsoid;
+use bevy::{
+    asset::load_internal_asset,
+    core_pipeline::{
+        core_3d::graph::{Core3d, Node3d},
+        fullscreen_vertex_shader::fullscreen_shader_vertex_state,
+    },
+    ecs::query::QueryItem,
+    prelude::*,
+    render::{
+        render_graph::{
+            NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner,
+        },
+        render_resource::{
+            binding_types::{texture_2d, texture_storage_2d, uniform_buffer},
+            BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, CachedComputePipelineId,
+            CachedRenderPipelineId, ColorTargetState, ColorWrites, ComputePassDescriptor,
+            ComputePipelineDescriptor, Extent3d, FragmentState, MultisampleState, PipelineCache,
+            PrimitiveState, RenderPassDescriptor, RenderPipelineDescriptor, ShaderStages,
+            SpecializedRenderPipeline, SpecializedRenderPipelines, StorageTextureAccess,
+            TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType, TextureUsages,
+            UniformBuffer,
+        },
+        renderer::{RenderContext, RenderDevice, RenderQueue},
+        texture::{BevyDefault, CachedTexture, TextureCache},
+        view::{ExtractedView, ViewTarget, ViewUniform, ViewUniformOffset, ViewUniforms},
+        Extract, ExtractSchedule, Render, RenderApp, RenderSet,
+    },
+};
+
+const RAYMARCH_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(81925384670213941722);
+const BLIT_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(81925384670213941723);
+
+// The number of invocations along each axis of a workgroup in `raymarch.wgsl`
+const WORKGROUP_SIZE: u32 = 8;
+
+/// The `RaymarchLabel` names the render graph node which marches rays through the scene for each
+/// 3D camera, replacing the output of the main pass.
+#[derive(RenderLabel, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RaymarchLabel;
+
+// Registers the shaders, the extraction of the scene, and the raymarch node in the render graph.
+// The pipelines are created by `finish_raymarch`, once the render device exists.
+pub(crate) fn build_raymarch(app: &mut App) {
+    load_internal_asset!(
+        app,
+        RAYMARCH_SHADER_HANDLE,
+        "raymarch.wgsl",
+        Shader::from_wgsl
+    );
+    load_internal_asset!(
+        app,
+        BLIT_SHADER_HANDLE,
+        "raymarch_blit.wgsl",
+        Shader::from_wgsl
+    );
+
+    let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+        return;
+    };
+    render_app
+        .init_resource::<RaymarchSceneBuffer>()
+        .init_resource::<SpecializedRenderPipelines<RaymarchPipeline>>()
+        .add_systems(ExtractSchedule, extract_scene)
+        .add_systems(
+            Render,
+            (
+                prepare_blit_pipelines.in_set(RenderSet::Prepare),
+                (prepare_scene_buffer, prepare_output_textures).in_set(RenderSet::PrepareResources),
+            ),
+        )
+        .add_render_graph_node::<ViewNodeRunner<RaymarchNode>>(Core3d, RaymarchLabel)
+        .add_render_graph_edges(
+            Core3d,
+            (Node3d::EndMainPass, RaymarchLabel, Node3d::Tonemapping),
+        );
+}
+
+pub(crate) fn finish_raymarch(app: &mut App) {
+    let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+        return;
+    };
+    render_app.init_resource::<RaymarchPipeline>();
+}
+
+/// The `RaymarchScene` struct is the scene uniform read by `raymarch.wgsl`. For this first pass the
+/// scene is a single sphere, taken from the first `Ellipsoid` in the world, packed as its center
+/// followed by its radius. A radius of zero means there is nothing to march against, and every
+/// pixel is cleared to the background.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq)]
+struct RaymarchScene(Vec4);
+
+// The scene uniform buffer, written once per frame from the extracted `RaymarchScene`.
+#[derive(Resource, Default)]
+struct RaymarchSceneBuffer(UniformBuffer<Vec4>);
+
+// The texture the compute pass writes to for a view, sized to its viewport. Surface textures are
+// rarely usable as storage textures, so it is copied to the view target by the blit pipeline, the
+// same as the fallback path of the standalone renderer.
+#[derive(Component)]
+struct RaymarchOutput(CachedTexture);
+
+// The blit pipeline specialized for the format and sample count of a view's target.
+#[derive(Component)]
+struct RaymarchBlitPipeline(CachedRenderPipelineId);
+
+#[derive(Resource)]
+struct RaymarchPipeline {
+    march_layout: BindGroupLayout,
+    blit_layout: BindGroupLayout,
+    march_pipeline: CachedComputePipelineId,
+}
+
+impl FromWorld for RaymarchPipeline {
+    fn from_world(render_world: &mut World) -> Self {
+        let render_device = render_world.resource::<RenderDevice>();
+        let march_layout = render_device.create_bind_group_layout(
+            "raymarch_march_bind_group_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    uniform_buffer::<ViewUniform>(true),
+                    uniform_buffer::<Vec4>(false),
+                    texture_storage_2d(TextureFormat::Rgba8Unorm, StorageTextureAccess::WriteOnly),
+                ),
+            ),
+        );
+        let blit_layout = render_device.create_bind_group_layout(
+            "raymarch_blit_bind_group_layout",
+            &BindGroupLayoutEntries::single(
+                ShaderStages::FRAGMENT,
+                texture_2d(TextureSampleType::Float { filterable: false }),
+            ),
+        );
+
+        let march_pipeline = render_world
+            .resource::<PipelineCache>()
+            .queue_compute_pipeline(ComputePipelineDescriptor {
+                label: Some("raymarch_march_pipeline".into()),
+                layout: vec![march_layout.clone()],
+                push_constant_ranges: Vec::new(),
+                shader: RAYMARCH_SHADER_HANDLE,
+                shader_defs: Vec::new(),
+                entry_point: "march".into(),
+            });
+
+        Self {
+            march_layout,
+            blit_layout,
+            march_pipeline,
+        }
+    }
+}
+
+impl SpecializedRenderPipeline for RaymarchPipeline {
+    // The format of the view target, and the number of samples of its main texture. The blit draws
+    // into the multisampled main texture when MSAA is enabled, so the pipeline must match it.
+    type Key = (TextureFormat, u32);
+
+    fn specialize(&self, (format, samples): Self::Key) -> RenderPipelineDescriptor {
+        RenderPipelineDescriptor {
+            label: Some("raymarch_blit_pipeline".into()),
+            layout: vec![self.blit_layout.clone()],
+            vertex: fullscreen_shader_vertex_state(),
+            fragment: Some(FragmentState {
+                shader: BLIT_SHADER_HANDLE,
+                shader_defs: Vec::new(),
+                entry_point: "fragment".into(),
+                targets: vec![Some(ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState {
+                count: samples,
+                ..default()
+            },
+            push_constant_ranges: Vec::new(),
+        }
+    }
+}
+
+// Only a single primitive is marched so far, so any ellipsoids after the first are ignored.
+fn extract_scene(
+    mut commands: Commands,
+    ellipsoids: Extract<Query<(&Ellipsoid, &GlobalTransform)>>,
+) {
+    let scene = ellipsoids
+        .iter()
+        .next()
+        .map(|(ellipsoid, transform)| {
+            RaymarchScene(transform.translation().extend(ellipsoid.radius))
+        })
+        .unwrap_or_default();
+    commands.insert_resource(scene);
+}
+
+fn prepare_scene_buffer(
+    scene: Res<RaymarchScene>,
+    mut buffer: ResMut<RaymarchSceneBuffer>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    buffer.0.set(scene.0);
+    buffer.0.write_buffer(&render_device, &render_queue);
+}
+
+fn prepare_output_textures(
+    mut commands: Commands,
+    mut texture_cache: ResMut<TextureCache>,
+    render_device: Res<RenderDevice>,
+    views: Query<(Entity, &ExtractedView), With<Camera3d>>,
+) {
+    for (entity, view) in &views {
+        let texture = texture_cache.get(
+            &render_device,
+            TextureDescriptor {
+                label: Some("raymarch_output_texture"),
+                size: Extent3d {
+                    width: view.viewport.z.max(1),
+                    height: view.viewport.w.max(1),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::Rgba8Unorm,
+                usage: TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+        );
+        commands.entity(entity).insert(RaymarchOutput(texture));
+    }
+}
+
+fn prepare_blit_pipelines(
+    mut commands: Commands,
+    pipeline_cache: Res<PipelineCache>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<RaymarchPipeline>>,
+    raymarch_pipeline: Res<RaymarchPipeline>,
+    msaa: Res<Msaa>,
+    views: Query<(Entity, &ExtractedView), With<Camera3d>>,
+) {
+    for (entity, view) in &views {
+        let format = if view.hdr {
+            ViewTarget::TEXTURE_FORMAT_HDR
+        } else {
+            TextureFormat::bevy_default()
+        };
+        let pipeline_id = pipelines.specialize(
+            &pipeline_cache,
+            &raymarch_pipeline,
+            (format, msaa.samples()),
+        );
+        commands
+            .entity(entity)
+            .insert(RaymarchBlitPipeline(pipeline_id));
+    }
+}
+
+// The node runs the compute pass into the view's output texture, then copies that texture over the
+// view target. Nothing is drawn until both pipelines have finished compiling.
+#[derive(Default)]
+struct RaymarchNode;
+
+impl ViewNode for RaymarchNode {
+    type ViewQuery = (
+        &'static ViewTarget,
+        &'static ViewUniformOffset,
+        &'static RaymarchOutput,
+        &'static RaymarchBlitPipeline,
+    );
+
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (target, view_offset, output, blit_pipeline): QueryItem<Self::ViewQuery>,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let raymarch_pipeline = world.resource::<RaymarchPipeline>();
+        let (Some(march), Some(blit)) = (
+            pipeline_cache.get_compute_pipeline(raymarch_pipeline.march_pipeline),
+            pipeline_cache.get_render_pipeline(blit_pipeline.0),
+        ) else {
+            return Ok(());
+        };
+        let (Some(view_binding), Some(scene_binding)) = (
+            world.resource::<ViewUniforms>().uniforms.binding(),
+            world.resource::<RaymarchSceneBuffer>().0.binding(),
+        ) else {
+            return Ok(());
+        };
+
+        let render_device = render_context.render_device();
+        let march_bind_group = render_device.create_bind_group(
+            "raymarch_march_bind_group",
+            &raymarch_pipeline.march_layout,
+            &BindGroupEntries::sequential((view_binding, scene_binding, &output.0.default_view)),
+        );
+        let blit_bind_group = render_device.create_bind_group(
+            "raymarch_blit_bind_group",
+            &raymarch_pipeline.blit_layout,
+            &BindGroupEntries::single(&output.0.default_view),
+        );
+
+        let size = output.0.texture.size();
+        {
+            let mut compute_pass =
+                render_context
+                    .command_encoder()
+                    .begin_compute_pass(&ComputePassDescriptor {
+                        label: Some("raymarch_march_pass"),
+                        timestamp_writes: None,
+                    });
+            compute_pass.set_pipeline(march);
+            compute_pass.set_bind_group(0, &march_bind_group, &[view_offset.offset]);
+            compute_pass.dispatch_workgroups(
+                size.width.div_ceil(WORKGROUP_SIZE),
+                size.height.div_ceil(WORKGROUP_SIZE),
+                1,
+            );
+        }
+
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("raymarch_blit_pass"),
+            color_attachments: &[Some(target.get_color_attachment())],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_render_pipeline(blit);
+        render_pass.set_bind_group(0, &blit_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RaymarchLabel;
+    use crate::RasterlessRenderPlugin;
+    use bevy::{
+        core_pipeline::core_3d::graph::{Core3d, Node3d},
+        prelude::*,
+        render::{
+            render_graph::{EmptyNode, RenderGraph, RenderLabel},
+            RenderApp,
+        },
+    };
+
+    // Test that building the plugin registers the raymarch node in the 3D graph, between the main
+    // pass and tonemapping. The render sub-app is built by hand, since there is no adapter to
+    // create a real one with.
+    #[test]
+    fn test_raymarch_node_registered() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Shader>();
+
+        let mut core_3d = RenderGraph::default();
+        core_3d.add_node(Node3d::EndMainPass, EmptyNode);
+        core_3d.add_node(Node3d::Tonemapping, EmptyNode);
+        let mut render_graph = RenderGraph::default();
+        render_graph.add_sub_graph(Core3d, core_3d);
+        let mut render_app = SubApp::new();
+        render_app.insert_resource(render_graph);
+        app.insert_sub_app(RenderApp, render_app);
+
+        app.add_plugins(RasterlessRenderPlugin);
+
+        let render_graph = app.sub_app(RenderApp).world().resource::<RenderGraph>();
+        let core_3d = render_graph.get_sub_graph(Core3d).unwrap();
+        let node = core_3d.get_node_state(RaymarchLabel).unwrap();
+        let inputs: Vec<_> = node
+            .edges
+            .input_edges()
+            .iter()
+            .map(|edge| edge.get_output_node())
+            .collect();
+        assert_eq!(inputs, [Node3d::EndMainPass.intern()]);
+        let outputs: Vec<_> = node
+            .edges
+            .output_edges()
+            .iter()
+            .map(|edge| edge.get_input_node())
+            .collect();
+        assert_eq!(outputs, [Node3d::Tonemapping.intern()]);
+    }
+}
@@ -0,0 +1,60 @@
+#import bevy_render::view::View
+
+// A single sphere, which is all the Bevy render node marches against so far. A radius of zero
+// means the scene is empty.
+struct Scene {
+    center: vec3<f32>,
+    radius: f32,
+}
+
+@group(0) @binding(0) var<uniform> view: View;
+@group(0) @binding(1) var<uniform> scene: Scene;
+@group(0) @binding(2) var output: texture_storage_2d<rgba8unorm, write>;
+
+const MAX_STEPS: u32 = 128u;
+const HIT_EPSILON: f32 = 0.001;
+const MAX_DISTANCE: f32 = 1000.0;
+const BACKGROUND: vec4<f32> = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+const LIGHT_DIRECTION: vec3<f32> = vec3<f32>(0.408, 0.816, -0.408);
+
+// Returns the world position of a point in normalized device coordinates.
+fn ndc_to_world(ndc: vec3<f32>) -> vec3<f32> {
+    let world = view.world_from_clip * vec4<f32>(ndc, 1.0);
+    return world.xyz / world.w;
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn march(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(output);
+    if id.x >= size.x || id.y >= size.y {
+        return;
+    }
+
+    // Bevy uses a reversed depth, so the near plane is at a depth of 1. The ray is cast from the
+    // near plane through a point further away, which works for both perspective and orthographic
+    // projections.
+    let uv = (vec2<f32>(id.xy) + 0.5) / vec2<f32>(size);
+    let ndc = vec2<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0);
+    let origin = ndc_to_world(vec3<f32>(ndc, 1.0));
+    let direction = normalize(ndc_to_world(vec3<f32>(ndc, 0.5)) - origin);
+
+    var color = BACKGROUND;
+    if scene.radius > 0.0 {
+        var distance = 0.0;
+        for (var step = 0u; step < MAX_STEPS; step++) {
+            let position = origin + direction * distance;
+            let to_surface = length(position - scene.center) - scene.radius;
+            if to_surface < HIT_EPSILON {
+                let normal = normalize(position - scene.center);
+                let light = max(dot(normal, LIGHT_DIRECTION), 0.0);
+                color = vec4<f32>(vec3<f32>(0.1 + 0.9 * light), 1.0);
+                break;
+            }
+            distance += to_surface;
+            if distance > MAX_DISTANCE {
+                break;
+            }
+        }
+    }
+    textureStore(output, id.xy, color);
+}
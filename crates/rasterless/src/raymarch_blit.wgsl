@@ -0,0 +1,10 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+@group(0) @binding(0) var output: texture_2d<f32>;
+
+// Copies the output of the raymarch compute pass over the whole view target.
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(output));
+    return textureLoad(output, vec2<i32>(min(in.uv * size, size - 1.0)), 0);
+}
@@ -0,0 +1,84 @@
+use bytemuck::{Pod, Zeroable};
+
+/// The `RenderSettings` struct holds the options of the raymarching pass which are not part of the
+/// scene itself, such as whether shadows are drawn and how far each ray may march.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct RenderSettings {
+    /// Whether rays are marched from each surface towards each light to find shadows. Disabling
+    /// shadows skips those extra rays, which is much cheaper.
+    pub(crate) shadows_enabled: bool,
+    /// The width of the penumbra at the edge of a shadow, relative to the distance from the
+    /// occluder to the shaded surface. Zero gives hard shadows, and larger values give softer
+    /// shadows.
+    pub(crate) shadow_softness: f32,
+    /// The most steps a primary ray may take before it is treated as a miss. Fewer steps are
+    /// cheaper, but rays which graze surfaces may stop short of them.
+    pub(crate) max_steps: u32,
+    /// The distance from a surface within which a ray is considered to have hit it.
+    pub(crate) hit_epsilon: f32,
+    /// The distance a primary ray may travel from the camera before it is treated as a miss.
+    pub(crate) max_distance: f32,
+}
+
+/// The `RenderSettingsUniform` struct is the data uploaded to the render settings uniform buffer.
+/// Its layout must match the `RenderSettings` struct in `compute.wgsl`: the shadow softness at
+/// offset 0, the shadows flag at offset 4, the max steps at offset 8, the hit epsilon at offset 12,
+/// the max distance at offset 16, and padding up to a total of 32 bytes.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub(crate) struct RenderSettingsUniform {
+    shadow_softness: f32,
+    shadows_enabled: u32,
+    max_steps: u32,
+    hit_epsilon: f32,
+    max_distance: f32,
+    _padding: [f32; 3],
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            shadows_enabled: true,
+            shadow_softness: 0.05,
+            max_steps: 256,
+            hit_epsilon: 0.001,
+            max_distance: 100.0,
+        }
+    }
+}
+
+impl RenderSettings {
+    pub(crate) fn to_uniform_data(self) -> RenderSettingsUniform {
+        RenderSettingsUniform {
+            shadow_softness: self.shadow_softness,
+            shadows_enabled: self.shadows_enabled as u32,
+            max_steps: self.max_steps,
+            hit_epsilon: self.hit_epsilon,
+            max_distance: self.max_distance,
+            _padding: [0.0; 3],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RenderSettings, RenderSettingsUniform};
+    use std::mem::size_of;
+
+    // Test that the uniform fills two whole 16-byte uniform slots, and that disabling shadows clears
+    // the flag read by the shader.
+    #[test]
+    fn test_uniform_data() {
+        assert_eq!(size_of::<RenderSettingsUniform>(), 32);
+
+        let settings = RenderSettings {
+            shadows_enabled: false,
+            ..Default::default()
+        };
+        let uniform = settings.to_uniform_data();
+        assert_eq!(uniform.shadows_enabled, 0);
+        assert_eq!(uniform.shadow_softness, settings.shadow_softness);
+        assert_eq!(uniform.max_steps, settings.max_steps);
+        assert_eq!(uniform.max_distance, settings.max_distance);
+    }
+}
@@ -0,0 +1,272 @@
+use bytemuck::{bytes_of, cast_slice, Pod};
+use std::{collections::BTreeSet, mem::size_of};
+
+/// The size in bytes of the header at the start of a counted storage buffer, which holds the number
+/// of entries. The header is padded to 16 bytes because WGSL aligns the array of entries that
+/// follows it to 16 bytes.
+const HEADER_SIZE: usize = 16;
+
+/// Returns the contents of a storage buffer holding a count followed by a runtime-sized array of
+/// entries, such as the `Lights` and `Primitives` structs in `compute.wgsl`. Each entry must have the
+/// layout of the matching WGSL struct, with a size that is a multiple of 16 bytes.
+///
+/// A storage buffer bound to a runtime-sized array must have room for at least one element, so when
+/// there are no entries a single zeroed entry is appended. The shader never reads it, because the
+/// header reports zero entries.
+pub(crate) fn counted_array_contents<T: Pod>(entries: &[T]) -> Vec<u8> {
+    let mut contents = vec![0u8; HEADER_SIZE];
+    contents[..4].copy_from_slice(bytes_of(&(entries.len() as u32)));
+    contents.extend_from_slice(cast_slice(entries));
+    if entries.is_empty() {
+        contents.extend_from_slice(bytes_of(&T::zeroed()));
+    }
+    contents
+}
+
+/// The `StorageUpdate` enum describes a change that must be made to a GPU storage buffer to bring it
+/// in line with a `SceneBuffer`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum StorageUpdate {
+    /// The buffer is too small, and must be replaced by a new buffer with these contents.
+    Reallocate(Vec<u8>),
+    /// These contents must be written over the buffer, starting at the given byte offset.
+    Write { offset: u64, contents: Vec<u8> },
+}
+
+/// The `SceneBuffer` struct keeps a CPU-side copy of a counted storage buffer, as laid out by
+/// `counted_array_contents`, and tracks which of its entries have changed since the buffer was last
+/// updated. This lets a single entry be changed by writing only its own bytes, rather than uploading
+/// every entry again.
+///
+/// Entries live in slots which keep their index until they are removed. Removed slots are filled
+/// with an empty entry, which the shader must ignore, and are kept on a free list to be reused by
+/// the next insertion. The buffer has room for more slots than are in use, and it is only
+/// reallocated when an insertion needs more slots than it has room for, at which point its capacity
+/// is at least doubled.
+pub(crate) struct SceneBuffer<T: Pod> {
+    entries: Vec<T>,
+    free: Vec<usize>,
+    empty: T,
+    capacity: usize,
+    reallocate: bool,
+    header_changed: bool,
+    changed: BTreeSet<usize>,
+}
+
+impl<T: Pod> SceneBuffer<T> {
+    /// Creates a new `SceneBuffer` without any entries, where removed entries are replaced by the
+    /// given empty entry. The buffer starts with room for a single entry, because a storage buffer
+    /// bound to a runtime-sized array must have room for at least one.
+    pub(crate) fn new(empty: T) -> Self {
+        Self {
+            entries: Vec::new(),
+            free: Vec::new(),
+            empty,
+            capacity: 1,
+            reallocate: false,
+            header_changed: false,
+            changed: BTreeSet::new(),
+        }
+    }
+
+    /// Returns the whole contents of the storage buffer, including room for unused slots.
+    pub(crate) fn contents(&self) -> Vec<u8> {
+        let mut contents = vec![0u8; HEADER_SIZE];
+        contents[..4].copy_from_slice(bytes_of(&(self.entries.len() as u32)));
+        contents.extend_from_slice(cast_slice(&self.entries));
+        for _ in self.entries.len()..self.capacity {
+            contents.extend_from_slice(bytes_of(&self.empty));
+        }
+        contents
+    }
+
+    /// Replaces every entry with the given entries, which take the slots from zero onwards. Changes
+    /// to slots beyond the new entries are dropped, since the shader no longer reads them.
+    pub(crate) fn set_all(&mut self, entries: &[T]) {
+        self.entries = entries.to_vec();
+        self.free.clear();
+        self.header_changed = true;
+        self.changed = (0..entries.len()).collect();
+        self.reserve();
+    }
+
+    /// Inserts an entry into the first free slot, or a new slot if none are free, and returns the
+    /// index of its slot.
+    pub(crate) fn insert(&mut self, entry: T) -> usize {
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.entries[index] = entry;
+                index
+            }
+            None => {
+                self.entries.push(entry);
+                self.header_changed = true;
+                self.reserve();
+                self.entries.len() - 1
+            }
+        };
+        self.changed.insert(index);
+        index
+    }
+
+    /// Replaces the entry in the slot with the given index.
+    ///
+    /// Panics if the slot is not in use.
+    pub(crate) fn update(&mut self, index: usize, entry: T) {
+        assert!(
+            index < self.entries.len() && !self.free.contains(&index),
+            "slot {index} is not in use"
+        );
+        self.entries[index] = entry;
+        self.changed.insert(index);
+    }
+
+    /// Removes the entry in the slot with the given index, freeing the slot for reuse.
+    ///
+    /// Panics if the slot is not in use.
+    pub(crate) fn remove(&mut self, index: usize) {
+        self.update(index, self.empty);
+        self.free.push(index);
+    }
+
+    /// Returns the updates needed to bring the storage buffer in line with the entries, and clears
+    /// the record of changed entries. Neighbouring changed entries are combined into one write.
+    pub(crate) fn take_updates(&mut self) -> Vec<StorageUpdate> {
+        let changed = std::mem::take(&mut self.changed);
+        let header_changed = std::mem::take(&mut self.header_changed);
+        if std::mem::take(&mut self.reallocate) {
+            return vec![StorageUpdate::Reallocate(self.contents())];
+        }
+
+        let mut updates = Vec::new();
+        if header_changed {
+            updates.push(StorageUpdate::Write {
+                offset: 0,
+                contents: bytes_of(&(self.entries.len() as u32)).to_vec(),
+            });
+        }
+        let mut changed = changed.into_iter().peekable();
+        while let Some(start) = changed.next() {
+            let mut end = start + 1;
+            while changed.next_if_eq(&end).is_some() {
+                end += 1;
+            }
+            updates.push(StorageUpdate::Write {
+                offset: (HEADER_SIZE + start * size_of::<T>()) as u64,
+                contents: cast_slice(&self.entries[start..end]).to_vec(),
+            });
+        }
+        updates
+    }
+
+    // Grows the capacity to fit every slot, if it does not already.
+    fn reserve(&mut self) {
+        if self.entries.len() > self.capacity {
+            self.capacity = self.entries.len().max(self.capacity * 2);
+            self.reallocate = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SceneBuffer, StorageUpdate, HEADER_SIZE};
+    use bytemuck::bytes_of;
+
+    // Returns a buffer holding the given entries, with its initial updates already taken.
+    fn uploaded(entries: &[[u32; 4]]) -> SceneBuffer<[u32; 4]> {
+        let mut buffer = SceneBuffer::new([0; 4]);
+        buffer.set_all(entries);
+        buffer.take_updates();
+        buffer
+    }
+
+    // Test that updating a single entry writes only that entry's bytes, at its offset.
+    #[test]
+    fn test_update_writes_single_entry() {
+        let mut buffer = uploaded(&[[1; 4], [2; 4], [3; 4]]);
+        buffer.update(1, [5; 4]);
+        assert_eq!(
+            buffer.take_updates(),
+            vec![StorageUpdate::Write {
+                offset: (HEADER_SIZE + 16) as u64,
+                contents: bytes_of(&[5u32; 4]).to_vec(),
+            }]
+        );
+        assert!(buffer.take_updates().is_empty());
+    }
+
+    // Test that removed slots are emptied and reused, without growing the buffer.
+    #[test]
+    fn test_remove_reuses_slot() {
+        let mut buffer = uploaded(&[[1; 4], [2; 4]]);
+        buffer.remove(0);
+        assert_eq!(buffer.insert([7; 4]), 0);
+        assert_eq!(
+            buffer.take_updates(),
+            vec![StorageUpdate::Write {
+                offset: HEADER_SIZE as u64,
+                contents: bytes_of(&[7u32; 4]).to_vec(),
+            }]
+        );
+    }
+
+    // Test that inserting beyond the capacity reallocates the buffer with the existing entries in
+    // place, and room to spare for further insertions.
+    #[test]
+    fn test_growth_preserves_entries() {
+        let mut buffer = uploaded(&[[1; 4], [2; 4]]);
+        assert_eq!(buffer.insert([3; 4]), 2);
+        let updates = buffer.take_updates();
+        let [StorageUpdate::Reallocate(contents)] = &updates[..] else {
+            panic!("expected a reallocation, found {updates:?}");
+        };
+        assert_eq!(contents.len(), HEADER_SIZE + 4 * 16);
+        assert_eq!(contents[..4], 3u32.to_le_bytes());
+        assert_eq!(
+            contents[HEADER_SIZE..HEADER_SIZE + 16],
+            *bytes_of(&[1u32; 4])
+        );
+        assert_eq!(
+            contents[HEADER_SIZE + 32..HEADER_SIZE + 48],
+            *bytes_of(&[3u32; 4])
+        );
+
+        // The fourth entry fits in the spare room, so only it and the count are written
+        buffer.insert([4; 4]);
+        assert_eq!(buffer.take_updates().len(), 2);
+    }
+
+    // Test that shrinking the entries drops pending changes to the slots beyond them, so that only
+    // the count and the remaining entry are written.
+    #[test]
+    fn test_set_all_shrinks() {
+        let mut buffer = SceneBuffer::new([0; 4]);
+        buffer.insert([1; 4]);
+        buffer.insert([2; 4]);
+        buffer.insert([3; 4]);
+        buffer.set_all(&[[4; 4]]);
+        let updates = buffer.take_updates();
+        let [StorageUpdate::Reallocate(contents)] = &updates[..] else {
+            panic!("expected a reallocation, found {updates:?}");
+        };
+        assert_eq!(contents[..4], 1u32.to_le_bytes());
+
+        buffer.insert([5; 4]);
+        buffer.insert([6; 4]);
+        buffer.set_all(&[[7; 4]]);
+        assert_eq!(
+            buffer.take_updates(),
+            vec![
+                StorageUpdate::Write {
+                    offset: 0,
+                    contents: 1u32.to_le_bytes().to_vec(),
+                },
+                StorageUpdate::Write {
+                    offset: HEADER_SIZE as u64,
+                    contents: bytes_of(&[7u32; 4]).to_vec(),
+                },
+            ]
+        );
+    }
+}
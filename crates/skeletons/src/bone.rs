@@ -1,8 +1,11 @@
 // ██▄ ▄▀▄ █▄ █ ██▀   █▀▄ ▄▀▀
 // █▄█ ▀▄▀ █ ▀█ █▄▄ ▄ █▀▄ ▄██
 //! This module describes the `Bone` component. It implements both accessor functions for fields of
-//! the `Bone` struct and utilities for positioning a `Bone` relative to its parent.
+//! the `Bone` struct and utilities for positioning a `Bone` relative to its parent. It also describes
+//! the `RigidBoneBundle`, which carries the geometry and mass a physics backend needs to simulate a
+//! `Bone`.
 use bevy::prelude::*;
+use std::f32::consts::PI;
 
 /// The `Bone` component marks an entity as a member of a skeleton. It specifies both length and
 /// angle that, when applied to an extrusion from the parent, will create a line segment between the
@@ -13,10 +16,42 @@ use bevy::prelude::*;
 /// Both components of the angle vector are measured in radians. The x component of the angle vector
 /// describes a rotation about the parent's direction as the axis, and the y component describes a
 /// 'latitudinal' rotation that bends back towards the negative of the parent's direction.
-#[derive(Clone, Copy, Component)]
+///
+/// A bone may also be rolled about its own direction by a `roll` angle, in radians. Roll does not
+/// change where the bone points, but it turns the bone's tangent, and so turns both its cross-section
+/// and the frame its children are positioned in.
+///
+/// A bone may optionally carry a mass and a radius, which describe it as a solid capsule for physics
+/// simulation.
+#[derive(Clone, Copy, Component, Debug, PartialEq)]
 pub struct Bone {
     length: f32,
     angle: Vec2,
+    roll: f32,
+    mass: Option<f32>,
+    radius: Option<f32>,
+}
+
+/// The `BoneCollider` component describes the capsule shape of a `Bone` for collision detection.
+/// The capsule is a cylinder running the length of the bone, capped by a hemisphere of the same
+/// radius at each end.
+#[derive(Clone, Copy, Component, Debug, PartialEq)]
+pub struct BoneCollider {
+    /// The length of the cylinder between the two hemispheres, in world units.
+    pub length: f32,
+    /// The radius of the cylinder and hemispheres, in world units.
+    pub radius: f32,
+}
+
+/// The `RigidBoneBundle` pairs a `Bone` with the components a physics backend needs to simulate it
+/// as a rigid body. It is not tied to any particular backend, so it carries only the collider
+/// geometry, while the mass and inertia can be read from the `Bone` itself.
+#[derive(Bundle, Clone, Copy)]
+pub struct RigidBoneBundle {
+    /// The bone to be simulated.
+    pub bone: Bone,
+    /// The capsule shape of the bone.
+    pub collider: BoneCollider,
 }
 
 /// The `ParentContext` type represents the direction and the tangent of the parent bone, which is
@@ -33,7 +68,31 @@ impl Bone {
     /// describes a 'latitudinal' rotation that bends back towards the negative of the parent's
     /// direction.
     pub fn new(length: f32, angle: Vec2) -> Self {
-        Self { length, angle }
+        Self {
+            length,
+            angle,
+            roll: 0.0,
+            mass: None,
+            radius: None,
+        }
+    }
+
+    /// Returns this bone with the given roll about its own direction, in radians.
+    pub fn with_roll(mut self, roll: f32) -> Self {
+        self.roll = roll;
+        self
+    }
+
+    /// Returns this bone with the given mass.
+    pub fn with_mass(mut self, mass: f32) -> Self {
+        self.mass = Some(mass);
+        self
+    }
+
+    /// Returns this bone with the given radius, in world units.
+    pub fn with_radius(mut self, radius: f32) -> Self {
+        self.radius = Some(radius);
+        self
     }
 
     /// Returns the length of the bone, in world units.
@@ -49,8 +108,104 @@ impl Bone {
         self.angle
     }
 
+    /// Returns the roll of the bone about its own direction, in radians.
+    pub fn roll(&self) -> f32 {
+        self.roll
+    }
+
+    /// Returns the mass of the bone, if it has one.
+    pub fn mass(&self) -> Option<f32> {
+        self.mass
+    }
+
+    /// Returns the radius of the bone, in world units, if it has one.
+    pub fn radius(&self) -> Option<f32> {
+        self.radius
+    }
+
+    /// Returns the moments of inertia of the bone as a solid capsule of uniform density, or `None`
+    /// if the bone has no mass or no radius. The first value is the moment about the bone's own
+    /// axis, and the second is the moment about any axis perpendicular to it through the bone's
+    /// center.
+    ///
+    /// The mass is split between the cylinder and the two hemispherical caps by volume. With a
+    /// cylinder of mass `m_c` and caps of total mass `m_s`, length `h` and radius `r`, the moments
+    /// are:
+    ///
+    /// - axial: `m_c * r^2 / 2 + m_s * 2r^2 / 5`
+    /// - transverse: `m_c * (r^2 / 4 + h^2 / 12) + m_s * (2r^2 / 5 + h^2 / 4 + 3hr / 8)`
+    pub fn inertia(&self) -> Option<(f32, f32)> {
+        let (mass, radius) = (self.mass?, self.radius?);
+        let (h, r) = (self.length, radius);
+
+        // Split the mass by volume
+        let cylinder_volume = PI * r * r * h;
+        let caps_volume = 4.0 / 3.0 * PI * r * r * r;
+        let total_volume = cylinder_volume + caps_volume;
+        if total_volume <= 0.0 {
+            return Some((0.0, 0.0));
+        }
+        let cylinder_mass = mass * cylinder_volume / total_volume;
+        let caps_mass = mass * caps_volume / total_volume;
+
+        let axial = cylinder_mass * r * r / 2.0 + caps_mass * 2.0 * r * r / 5.0;
+        let transverse = cylinder_mass * (r * r / 4.0 + h * h / 12.0)
+            + caps_mass * (2.0 * r * r / 5.0 + h * h / 4.0 + 3.0 * h * r / 8.0);
+        Some((axial, transverse))
+    }
+
+    /// Returns this bone reflected across the plane containing its parent's direction and tangent,
+    /// for building the opposite side of a bilaterally symmetric skeleton. Both the rotation about
+    /// the parent's direction and the roll are negated, while the length, mass and radius are kept.
+    pub fn mirror_x(&self) -> Bone {
+        Self {
+            angle: Vec2::new(-self.angle.x, self.angle.y),
+            roll: -self.roll,
+            ..*self
+        }
+    }
+
+    /// Interpolates between this bone and another, where a `t` of zero returns this bone and a `t`
+    /// of one returns the other. The length is interpolated linearly, while the angles and roll are
+    /// interpolated the short way around the circle, so that blending from just below a full turn
+    /// to just above zero does not swing the bone back through every angle in between.
+    ///
+    /// The mass and radius are interpolated linearly when both bones have them. Otherwise, the mass
+    /// or radius of whichever bone is nearer to `t` is used.
+    pub fn lerp(&self, other: &Bone, t: f32) -> Bone {
+        let optional = |a: Option<f32>, b: Option<f32>| match (a, b) {
+            (Some(a), Some(b)) => Some(a + (b - a) * t),
+            _ if t < 0.5 => a,
+            _ => b,
+        };
+        Bone {
+            length: self.length + (other.length - self.length) * t,
+            angle: Vec2::new(
+                lerp_angle(self.angle.x, other.angle.x, t),
+                lerp_angle(self.angle.y, other.angle.y, t),
+            ),
+            roll: lerp_angle(self.roll, other.roll, t),
+            mass: optional(self.mass, other.mass),
+            radius: optional(self.radius, other.radius),
+        }
+    }
+
+    /// Places the points of a two-dimensional cross-section around this bone, given the
+    /// `ParentContext` of its parent. The x and y components of each point are measured along the
+    /// bone's tangent and cotangent (the cross of its direction and tangent) respectively, so the
+    /// cross-section turns with the bone's roll. The returned offsets are relative to any point along
+    /// the bone.
+    pub fn cross_section(&self, parent_context: ParentContext, points: &[Vec2]) -> Vec<Vec3> {
+        let (direction, tangent) = self.derive(parent_context);
+        let cotangent = direction.cross(tangent);
+        points
+            .iter()
+            .map(|point| tangent * point.x + cotangent * point.y)
+            .collect()
+    }
+
     /// Takes a `ParentContext` as input, and returns a new `ParentContext` transformed by this
-    /// bone's angle vector.
+    /// bone's angle vector and roll.
     pub fn derive(&self, parent_context: ParentContext) -> ParentContext {
         // Get parent context values and ensure that they are normalized
         let (direction, tangent) = parent_context;
@@ -67,16 +222,47 @@ impl Bone {
         let latitudinal_rotation =
             Quat::from_axis_angle(direction.cross(rotated_tangent), self.angle.y);
 
+        let new_direction = latitudinal_rotation * direction;
+
+        // Roll the tangent about the bone's own direction
+        let roll_rotation = Quat::from_axis_angle(new_direction, self.roll);
+
         (
-            latitudinal_rotation * direction,
-            latitudinal_rotation * rotated_tangent,
+            new_direction,
+            roll_rotation * (latitudinal_rotation * rotated_tangent),
         )
     }
 }
 
+// Interpolates between two angles in radians along the shortest arc between them. The result is
+// measured from `from`, so it stays in the same turn as `from` and moves continuously with `t`. A
+// `t` of one or more returns `to` exactly, even when `to` lies in a different turn.
+fn lerp_angle(from: f32, to: f32, t: f32) -> f32 {
+    let difference = (to - from + PI).rem_euclid(2.0 * PI) - PI;
+    if t < 1.0 {
+        from + difference * t
+    } else {
+        to
+    }
+}
+
+impl RigidBoneBundle {
+    /// Creates a new `RigidBoneBundle` for the given bone, with a capsule collider matching its
+    /// length and radius. Returns `None` if the bone has no radius.
+    pub fn new(bone: Bone) -> Option<Self> {
+        Some(Self {
+            bone,
+            collider: BoneCollider {
+                length: bone.length(),
+                radius: bone.radius()?,
+            },
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::Bone;
+    use super::{Bone, RigidBoneBundle};
     use bevy::prelude::*;
     use std::f32::consts::PI;
 
@@ -93,4 +279,80 @@ mod tests {
         assert!(direction.distance(Vec3::Y) < 0.001);
         assert!(tangent.distance(Vec3::NEG_X) < 0.001);
     }
+
+    // Test the moments of inertia of a sample capsule against values worked out by hand, and that
+    // a bone without a radius has no inertia or collider.
+    #[test]
+    fn test_capsule_inertia() {
+        // A length of 2 and radius of 0.5 puts 3/4 of the mass in the cylinder
+        let bone = Bone::new(2.0, Vec2::ZERO).with_mass(10.0).with_radius(0.5);
+        let (axial, transverse) = bone.inertia().unwrap();
+        assert!((axial - 1.1875).abs() < 0.001);
+        assert!((transverse - 6.65625).abs() < 0.001);
+
+        let collider = RigidBoneBundle::new(bone).unwrap().collider;
+        assert_eq!((collider.length, collider.radius), (2.0, 0.5));
+
+        let bone = Bone::new(2.0, Vec2::ZERO).with_mass(10.0);
+        assert!(bone.inertia().is_none());
+        assert!(RigidBoneBundle::new(bone).is_none());
+    }
+
+    // Test that a quarter roll turns a cross-section which is not radially symmetric by a quarter
+    // turn about the bone, without changing the bone's direction.
+    #[test]
+    fn test_roll_turns_cross_section() {
+        let points = [Vec2::new(2.0, 0.0), Vec2::new(0.0, 1.0)];
+        let bone = Bone::new(1.0, Vec2::ZERO);
+        let unrolled = bone.cross_section((Vec3::X, Vec3::Y), &points);
+        assert!(unrolled[0].distance(Vec3::new(0.0, 2.0, 0.0)) < 0.001);
+        assert!(unrolled[1].distance(Vec3::new(0.0, 0.0, 1.0)) < 0.001);
+
+        let bone = bone.with_roll(PI / 2.0);
+        let rolled = bone.cross_section((Vec3::X, Vec3::Y), &points);
+        assert!(rolled[0].distance(Vec3::new(0.0, 0.0, 2.0)) < 0.001);
+        assert!(rolled[1].distance(Vec3::new(0.0, -1.0, 0.0)) < 0.001);
+        assert!(bone.derive((Vec3::X, Vec3::Y)).0.distance(Vec3::X) < 0.001);
+    }
+
+    // Test that interpolation starts at the first bone, ends at the second, and takes the short way
+    // around the circle between angles on either side of zero.
+    #[test]
+    fn test_lerp() {
+        let a = Bone::new(1.0, Vec2::new(0.5, -0.25)).with_mass(2.0);
+        let b = Bone::new(3.0, Vec2::new(1.5, 0.75))
+            .with_roll(1.0)
+            .with_mass(4.0);
+        assert_eq!(a.lerp(&b, 0.0), a);
+        let end = a.lerp(&b, 1.0);
+        assert!((end.length() - b.length()).abs() < 0.001);
+        assert!(end.angle().distance(b.angle()) < 0.001);
+        assert!((end.roll() - b.roll()).abs() < 0.001);
+        assert!((end.mass().unwrap() - 4.0).abs() < 0.001);
+
+        let middle = a.lerp(&b, 0.5);
+        assert!((middle.length() - 2.0).abs() < 0.001);
+        assert!(middle.angle().distance(Vec2::new(1.0, 0.25)) < 0.001);
+
+        // Halfway between just below a full turn and just above zero is a full turn, not a half
+        let a = Bone::new(1.0, Vec2::new(2.0 * PI - 0.2, 0.0));
+        let b = Bone::new(1.0, Vec2::new(0.2, 0.0));
+        let middle = a.lerp(&b, 0.5).angle().x;
+        assert!((middle - 2.0 * PI).abs() < 0.001);
+        assert_eq!(a.lerp(&b, 0.0), a);
+        assert_eq!(a.lerp(&b, 1.0), b);
+        assert_eq!(b.lerp(&a, 1.0), a);
+    }
+
+    // Test that interpolating across zero moves the angle by a small step on either side of the
+    // halfway point, rather than jumping by a full turn.
+    #[test]
+    fn test_lerp_angle_continuous() {
+        let a = Bone::new(1.0, Vec2::new(2.0 * PI - 0.2, 0.0));
+        let b = Bone::new(1.0, Vec2::new(0.2, 0.0));
+        let before = a.lerp(&b, 0.499).angle().x;
+        let after = a.lerp(&b, 0.501).angle().x;
+        assert!((after - before).abs() < 0.001);
+        assert!((a.lerp(&b, 0.999).angle().x - 2.0 * PI - 0.2).abs() < 0.001);
+    }
 }
@@ -3,7 +3,12 @@
 //! This module contains a set of functions used to generate skeleton descriptors. At the time of
 //! writing, these are mostly used for testing purposes, but in future they will be used to generate
 //! useful game content.
-use crate::SkeletonDescriptor;
+//!
+//! Generators which make random choices take a caller-provided random number generator, so that the
+//! same generator state always produces the same skeleton. Each has a `_from_seed` counterpart which
+//! seeds a `StdRng` from a `u64` for convenience.
+use crate::{skeleton::SkeletonDescElement, SkeletonDescriptor};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use std::f32::consts::PI;
 
 /// Converts an integer amount of angular degrees to radians.
@@ -43,3 +48,55 @@ pub fn stick_figure() -> SkeletonDescriptor {
         ),
     ])
 }
+
+/// Generates a skeleton descriptor that looks like a tree, with a trunk that splits into two or three
+/// branches at each level, down to the given depth. Each branch is shorter than its parent, and is
+/// rotated about its parent by a random amount.
+pub fn tree(rng: &mut impl Rng, depth: u32) -> SkeletonDescriptor {
+    // The trunk bends up from the skeleton's initial direction
+    let trunk = tree_branch(rng, 1.0, [0.0, degrees_to_radians(90)], depth);
+    SkeletonDescriptor::root(&[trunk])
+}
+
+/// Generates a skeleton descriptor that looks like a tree, in the same way as `tree`, using a random
+/// number generator seeded from the given seed.
+pub fn tree_from_seed(seed: u64, depth: u32) -> SkeletonDescriptor {
+    tree(&mut StdRng::seed_from_u64(seed), depth)
+}
+
+/// Generates a branch of a tree with the given length and angle, along with its child branches if
+/// there are levels of depth remaining.
+fn tree_branch(
+    rng: &mut impl Rng,
+    length: f32,
+    angle: [f32; 2],
+    depth: u32,
+) -> SkeletonDescElement {
+    if depth == 0 {
+        return SkeletonDescriptor::leaf(length, angle);
+    }
+
+    let children: Vec<SkeletonDescElement> = (0..rng.gen_range(2..=3))
+        .map(|_| {
+            let child_angle = [
+                rng.gen_range(0.0..2.0 * PI),
+                rng.gen_range(degrees_to_radians(20)..degrees_to_radians(45)),
+            ];
+            tree_branch(rng, length * 0.7, child_angle, depth - 1)
+        })
+        .collect();
+    SkeletonDescriptor::branch(length, angle, &children)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::tree_from_seed;
+
+    // Test that the same seed always generates the same tree, and that different seeds generate
+    // different trees.
+    #[test]
+    fn test_tree_seeded() {
+        assert_eq!(tree_from_seed(7, 4), tree_from_seed(7, 4));
+        assert_ne!(tree_from_seed(7, 4), tree_from_seed(8, 4));
+    }
+}
@@ -5,7 +5,7 @@
 //! which can easily be added to the app for the vizualization functionality. When the
 //! `bevy_console` feature is enabled, these visualizations can also be toggled on/off by using the
 //! `skeleton_gizmos` command in the console.
-use crate::{Bone, Skeleton};
+use crate::{skeleton::for_each_segment, Bone, Skeleton};
 use bevy::{color::palettes::css::RED, prelude::*};
 #[cfg(feature = "bevy_console")]
 use bevy_console::{AddConsoleCommand, ConsoleCommand};
@@ -44,38 +44,15 @@ fn draw_skeletons(
 
     // Start iteration with the roots (skeletons) and recurse to the leaves
     for (transform, children) in &skeletons {
-        // Iterate through all bones by using a stack. Necessary because bones are ordered
-        // hierarchically.
-        let mut stack = Vec::new();
-
-        // Push all bones that are direct children of the skeleton component
-        for child in children {
-            stack.push((Vec3::ZERO, (Vec3::X, Vec3::Y), child));
-        }
-
-        while !stack.is_empty() {
-            // We can unwrap because we know the stack is not empty
-            let (parent_position, parent_context, id) = stack.pop().unwrap();
-            let (bone, potential_children) = bones.get(id.clone()).unwrap();
-
-            // Position bone in space
-            let new_context = bone.derive(parent_context);
-            let new_position = parent_position + (new_context.0 * bone.length());
-
-            // Draw a line for the bone, while also transforming by the skeleton's transform
-            gizmos.line(
-                transform.transform_point(parent_position),
-                transform.transform_point(new_position),
-                RED,
-            );
-
-            // Add children to the stack
-            if let Some(children) = potential_children {
-                for child in children {
-                    stack.push((new_position, new_context, child));
-                }
-            }
-        }
+        // Draw a line for each bone
+        for_each_segment(
+            transform,
+            children,
+            |id| bones.get(id).ok(),
+            |start, end| {
+                gizmos.line(start, end, RED);
+            },
+        );
     }
 }
 
@@ -13,17 +13,28 @@ mod generators;
 mod gizmos;
 mod skeleton;
 
-pub use bone::Bone;
-pub use generators::{degrees_to_radians, stick_figure};
+pub use bone::{Bone, BoneCollider, RigidBoneBundle};
+pub use generators::{degrees_to_radians, stick_figure, tree, tree_from_seed};
 pub use gizmos::SkeletonGizmosPlugin;
-pub use skeleton::{Skeleton, SkeletonDescriptor};
+pub use skeleton::{
+    BoneName, Skeleton, SkeletonBones, SkeletonDescriptor, SkeletonError, SkeletonLimits,
+    SkeletonStats,
+};
 
 /// The `SkeletonPlugin` is the main plugin for the `prockit_skeletons` crate. It adds the
-/// required systems for skeleton construction.
+/// required systems for skeleton construction, and keeps the `SkeletonStats` of each skeleton up to
+/// date.
 pub struct SkeletonPlugin;
 
 impl Plugin for SkeletonPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, skeleton::construct_skeletons);
+        app.init_resource::<skeleton::SkeletonLimits>().add_systems(
+            Update,
+            (
+                skeleton::construct_skeletons,
+                skeleton::update_skeleton_stats,
+            )
+                .chain(),
+        );
     }
 }
@@ -8,24 +8,158 @@
 //! descriptor.
 use crate::Bone;
 use bevy::prelude::*;
+use std::{collections::HashMap, error::Error, fmt};
 
 /// A component marking an entity as the root for a parent/child hierachy of bones, considered in
 /// total as a "skeleton".
 #[derive(Component)]
 pub struct Skeleton;
 
+impl Skeleton {
+    /// Returns the start and end positions of every bone in the skeleton rooted at the given
+    /// entity, in world space. Bones are positioned by accumulating the angles and lengths of each
+    /// `Bone` down the hierarchy, starting from the skeleton's `Transform`, in the same way as the
+    /// skeleton gizmos draw them. Segments are returned in depth-first order.
+    ///
+    /// Returns an empty list if the entity has no children. This is useful for gameplay that needs
+    /// to know where bones are, such as attaching items to a bone's tip or hit detection.
+    pub fn bone_segments(world: &World, skeleton: Entity) -> Vec<(Vec3, Vec3)> {
+        let mut segments = Vec::new();
+        if let Some(children) = world.get::<Children>(skeleton) {
+            let transform = world
+                .get::<Transform>(skeleton)
+                .copied()
+                .unwrap_or_default();
+            for_each_segment(
+                &transform,
+                children,
+                |id| Some((world.get::<Bone>(id)?, world.get::<Children>(id))),
+                |start, end| segments.push((start, end)),
+            );
+        }
+        segments
+    }
+
+    /// Returns the number of bones, the depth, and the total length of the skeleton rooted at the
+    /// given entity. The same statistics are kept up to date in the `SkeletonStats` component of
+    /// every skeleton by the `SkeletonPlugin`.
+    pub fn stats(world: &World, skeleton: Entity) -> SkeletonStats {
+        world
+            .get::<Children>(skeleton)
+            .map(|children| {
+                SkeletonStats::compute(children, |id| {
+                    Some((world.get::<Bone>(id)?, world.get::<Children>(id)))
+                })
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// A component on the root `Skeleton` entity which summarizes the size of the skeleton, useful for
+/// choosing a level of detail or for debugging. It is updated every frame by the `SkeletonPlugin`.
+#[derive(Clone, Copy, Component, Debug, Default, PartialEq)]
+pub struct SkeletonStats {
+    /// The number of bones in the skeleton.
+    pub bone_count: usize,
+    /// The number of bones along the longest chain from the root to a leaf bone, so that a skeleton
+    /// whose bones are all direct children of the root has a depth of one.
+    pub max_depth: usize,
+    /// The sum of the lengths of every bone in the skeleton.
+    pub total_length: f32,
+}
+
+impl SkeletonStats {
+    // Walks the bones below a skeleton root with the given children, using the `lookup` function to
+    // fetch the `Bone` and children of each entity in the same way as `for_each_segment`.
+    fn compute<'a>(
+        children: &Children,
+        mut lookup: impl FnMut(Entity) -> Option<(&'a Bone, Option<&'a Children>)>,
+    ) -> Self {
+        let mut stats = Self::default();
+        let mut stack: Vec<(usize, Entity)> = children.iter().map(|child| (1, *child)).collect();
+        while let Some((depth, id)) = stack.pop() {
+            let Some((bone, potential_children)) = lookup(id) else {
+                continue;
+            };
+            stats.bone_count += 1;
+            stats.max_depth = stats.max_depth.max(depth);
+            stats.total_length += bone.length();
+            if let Some(children) = potential_children {
+                stack.extend(children.iter().map(|child| (depth + 1, *child)));
+            }
+        }
+        stats
+    }
+}
+
+/// A component naming a bone entity, copied from the name given to its `SkeletonDescElement` when the
+/// skeleton was constructed.
+#[derive(Clone, Copy, Component, Debug, PartialEq, Eq)]
+pub struct BoneName(pub &'static str);
+
+/// A component on the root `Skeleton` entity which maps the names of its bones to their entities, so
+/// that specific bones (such as "the left hand bone") can be found after the skeleton has been
+/// constructed. Only bones which were given a name in the `SkeletonDescriptor` are recorded.
+#[derive(Clone, Component, Debug, Default, PartialEq)]
+pub struct SkeletonBones(HashMap<&'static str, Entity>);
+
+impl SkeletonBones {
+    /// Returns the entity of the bone with the given name, if there is one.
+    pub fn get(&self, name: &str) -> Option<Entity> {
+        self.0.get(name).copied()
+    }
+
+    /// Returns an iterator over the names and entities of all named bones, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, Entity)> + '_ {
+        self.0.iter().map(|(name, entity)| (*name, *entity))
+    }
+}
+
 /// The `SkeletonDescElement` is a private struct used to describe a single bone, and link to the
 /// bones children, as a member of a parent `SkeletonDescriptor` object.
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct SkeletonDescElement {
     bone: Bone,
+    name: Option<&'static str>,
     children: Vec<Self>,
 }
 
+impl SkeletonDescElement {
+    /// Returns this element with its bone rolled about its own direction by the given angle, in
+    /// radians. This can be chained onto the `SkeletonDescriptor::branch` and
+    /// `SkeletonDescriptor::leaf` functions.
+    pub fn with_roll(mut self, roll: f32) -> Self {
+        self.bone = self.bone.with_roll(roll);
+        self
+    }
+
+    /// Returns this element with the given name. The constructed bone entity will have a `BoneName`
+    /// component, and can be looked up by name in the `SkeletonBones` component of its skeleton.
+    /// Names should be unique within a skeleton; if several bones share a name, only one of them
+    /// can be looked up by it.
+    pub fn with_name(mut self, name: &'static str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// Returns this element with a mirrored copy of each of its children added after them, as made
+    /// by `SkeletonDescriptor::mirror_x`. This lets one side of a symmetric skeleton be described
+    /// once, such as a single leg below the hips.
+    pub fn with_mirrored(mut self) -> Self {
+        let mirrored: Vec<_> = self
+            .children
+            .iter()
+            .map(SkeletonDescriptor::mirror_x)
+            .collect();
+        self.children.extend(mirrored);
+        self
+    }
+}
+
 /// The `SkeletonDescriptor` is used as a simply initialized object describing a skeleton/bone
 /// hierarchy. It will be consumed by a Bevy system and converted into multiple ECS entities in a
 /// parent/child hierarchy representing the total skeleton.
-#[derive(Component)]
+#[derive(Component, Debug, PartialEq)]
 pub struct SkeletonDescriptor {
     children: Vec<SkeletonDescElement>,
 }
@@ -83,6 +217,7 @@ impl SkeletonDescriptor {
     ) -> SkeletonDescElement {
         SkeletonDescElement {
             bone: Bone::new(length, Vec2::from_array(angle)),
+            name: None,
             children: children.to_vec(),
         }
     }
@@ -110,26 +245,232 @@ impl SkeletonDescriptor {
     pub fn leaf(length: f32, angle: [f32; 2]) -> SkeletonDescElement {
         SkeletonDescElement {
             bone: Bone::new(length, Vec2::from_array(angle)),
+            name: None,
             children: Vec::new(),
         }
     }
+
+    /// Returns a deep copy of the element and all of its children, with each bone mirrored by
+    /// `Bone::mirror_x`, so that only one side of a symmetric skeleton needs to be described. Names
+    /// are not copied, since they should be unique within a skeleton.
+    pub fn mirror_x(element: &SkeletonDescElement) -> SkeletonDescElement {
+        SkeletonDescElement {
+            bone: element.bone.mirror_x(),
+            name: None,
+            children: element.children.iter().map(Self::mirror_x).collect(),
+        }
+    }
+
+    /// Interpolates between the poses described by this descriptor and another, bone by bone, using
+    /// `Bone::lerp`. This can be used to blend between two poses of the same skeleton for
+    /// animation. The names of bones are taken from this descriptor.
+    ///
+    /// Returns `None` if the two descriptors do not have the same shape, meaning every bone must
+    /// have the same number of children in both.
+    pub fn lerp(&self, other: &SkeletonDescriptor, t: f32) -> Option<SkeletonDescriptor> {
+        Some(Self {
+            children: lerp_elements(&self.children, &other.children, t)?,
+        })
+    }
+
+    /// Checks that every bone of the descriptor has a finite, positive length and finite angles, and
+    /// that the skeleton is within the given limits. Returns the first problem found, if any.
+    /// Invalid descriptors are skipped by the `SkeletonPlugin` rather than constructed.
+    pub fn validate(&self, limits: &SkeletonLimits) -> Result<(), SkeletonError> {
+        let mut bone_count = 0;
+        let mut stack: Vec<(usize, &SkeletonDescElement)> =
+            self.children.iter().map(|child| (1, child)).collect();
+        while let Some((depth, element)) = stack.pop() {
+            let bone = &element.bone;
+            if !bone.length().is_finite() {
+                return Err(SkeletonError::NonFiniteLength(bone.length()));
+            }
+            if bone.length() <= 0.0 {
+                return Err(SkeletonError::NonPositiveLength(bone.length()));
+            }
+            if !bone.angle().is_finite() || !bone.roll().is_finite() {
+                return Err(SkeletonError::NonFiniteAngle(bone.angle(), bone.roll()));
+            }
+
+            bone_count += 1;
+            if depth > limits.max_depth {
+                return Err(SkeletonError::TooDeep(limits.max_depth));
+            }
+            if bone_count > limits.max_bones {
+                return Err(SkeletonError::TooManyBones(limits.max_bones));
+            }
+            stack.extend(element.children.iter().map(|child| (depth + 1, child)));
+        }
+        Ok(())
+    }
+}
+
+// Interpolates between two lists of matching elements, along with all of their children.
+fn lerp_elements(
+    from: &[SkeletonDescElement],
+    to: &[SkeletonDescElement],
+    t: f32,
+) -> Option<Vec<SkeletonDescElement>> {
+    if from.len() != to.len() {
+        return None;
+    }
+    from.iter()
+        .zip(to)
+        .map(|(from, to)| {
+            Some(SkeletonDescElement {
+                bone: from.bone.lerp(&to.bone, t),
+                name: from.name,
+                children: lerp_elements(&from.children, &to.children, t)?,
+            })
+        })
+        .collect()
+}
+
+/// The `SkeletonLimits` resource bounds the size of the skeletons that `SkeletonDescriptor::validate`
+/// accepts, guarding against runaway procedural generation. It can be replaced in the app to allow
+/// larger skeletons.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Resource)]
+pub struct SkeletonLimits {
+    /// The largest number of bones along any chain from the root to a leaf bone.
+    pub max_depth: usize,
+    /// The largest number of bones in the skeleton.
+    pub max_bones: usize,
+}
+
+impl Default for SkeletonLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 64,
+            max_bones: 4096,
+        }
+    }
+}
+
+/// The `SkeletonError` enum describes the reasons a `SkeletonDescriptor` may fail validation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SkeletonError {
+    /// A bone has a length which is not finite.
+    NonFiniteLength(f32),
+    /// A bone has a length which is zero or negative.
+    NonPositiveLength(f32),
+    /// A bone has an angle or roll which is not finite.
+    NonFiniteAngle(Vec2, f32),
+    /// The skeleton is deeper than the given maximum depth.
+    TooDeep(usize),
+    /// The skeleton has more bones than the given maximum bone count.
+    TooManyBones(usize),
+}
+
+impl fmt::Display for SkeletonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NonFiniteLength(length) => write!(f, "bone length {length} is not finite"),
+            Self::NonPositiveLength(length) => write!(f, "bone length {length} is not positive"),
+            Self::NonFiniteAngle(angle, roll) => {
+                write!(f, "bone angle {angle} with roll {roll} is not finite")
+            }
+            Self::TooDeep(max_depth) => {
+                write!(f, "skeleton is deeper than the limit of {max_depth} bones")
+            }
+            Self::TooManyBones(max_bones) => {
+                write!(f, "skeleton has more than the limit of {max_bones} bones")
+            }
+        }
+    }
+}
+
+impl Error for SkeletonError {}
+
+/// Walks the bones below a skeleton root with the given transform and children, positioning each
+/// bone relative to its parent, and calls `visit` with the world-space start and end of each bone.
+/// The `lookup` function fetches the `Bone` and children of an entity, and entities it returns
+/// `None` for are skipped along with their descendants.
+pub(crate) fn for_each_segment<'a>(
+    transform: &Transform,
+    children: &Children,
+    mut lookup: impl FnMut(Entity) -> Option<(&'a Bone, Option<&'a Children>)>,
+    mut visit: impl FnMut(Vec3, Vec3),
+) {
+    // Iterate through all bones by using a stack. Necessary because bones are ordered
+    // hierarchically.
+    let mut stack = Vec::new();
+
+    // Push all bones that are direct children of the skeleton component
+    for child in children {
+        stack.push((Vec3::ZERO, (Vec3::X, Vec3::Y), *child));
+    }
+
+    while let Some((parent_position, parent_context, id)) = stack.pop() {
+        let Some((bone, potential_children)) = lookup(id) else {
+            continue;
+        };
+
+        // Position bone in space
+        let new_context = bone.derive(parent_context);
+        let new_position = parent_position + (new_context.0 * bone.length());
+
+        // Report the bone, while also transforming by the skeleton's transform
+        visit(
+            transform.transform_point(parent_position),
+            transform.transform_point(new_position),
+        );
+
+        // Add children to the stack
+        if let Some(children) = potential_children {
+            for child in children {
+                stack.push((new_position, new_context, *child));
+            }
+        }
+    }
+}
+
+/// This system computes the `SkeletonStats` of every skeleton, inserting the component on skeletons
+/// which do not have it yet.
+pub(crate) fn update_skeleton_stats(
+    mut commands: Commands,
+    mut skeletons: Query<(Entity, &Children, Option<&mut SkeletonStats>), With<Skeleton>>,
+    bones: Query<(&Bone, Option<&Children>)>,
+) {
+    for (entity, children, current) in &mut skeletons {
+        let stats = SkeletonStats::compute(children, |id| bones.get(id).ok());
+        match current {
+            // Only write to the component when the stats change, to keep change detection useful
+            Some(mut current) => {
+                current.set_if_neq(stats);
+            }
+            None => {
+                commands.entity(entity).insert(stats);
+            }
+        }
+    }
 }
 
 /// This system consumes all entities containing a `SkeletonDescriptor` component and spawns a
 /// collection of entities into the ECS which match the parent/child hierarchy outlined in the
-/// `SkeletonDescriptor` component.
+/// `SkeletonDescriptor` component. Descriptors which fail validation against the `SkeletonLimits`
+/// resource, or the default limits if there is none, are logged and removed without being
+/// constructed.
 pub(crate) fn construct_skeletons(
     mut commands: Commands,
     skeleton_descriptors: Query<(Entity, &Transform, &SkeletonDescriptor)>,
+    limits: Option<Res<SkeletonLimits>>,
 ) {
+    let limits = limits.map(|limits| *limits).unwrap_or_default();
     for (entity, transform, skeleton_descriptor) in &skeleton_descriptors {
+        if let Err(error) = skeleton_descriptor.validate(&limits) {
+            warn!("Skipping invalid skeleton descriptor on {entity}: {error}");
+            commands.entity(entity).despawn();
+            continue;
+        }
+
         // Create the root Skeleton component
-        let id = commands.spawn((transform.clone(), Skeleton)).id();
+        let root_id = commands.spawn((transform.clone(), Skeleton)).id();
+        let mut bones = SkeletonBones::default();
 
         // Initialize the stack to be the children of the root component
         let mut stack = Vec::new();
         for child in &skeleton_descriptor.children {
-            stack.push((id, child));
+            stack.push((root_id, child));
         }
 
         // Iterate through the bone hierarchy using the stack
@@ -141,17 +482,258 @@ pub(crate) fn construct_skeletons(
             let id = commands.spawn(element.bone).id();
             commands.get_entity(parent_id).unwrap().add_child(id);
 
+            // Record the bone under its name, if it has one
+            if let Some(name) = element.name {
+                commands.entity(id).insert(BoneName(name));
+                bones.0.insert(name, id);
+            }
+
             // Add the children of this bone to the stack
             for child in &element.children {
                 stack.push((id, child));
             }
         }
 
-        // We finished adding the skeleton, so we remove the skeleton descriptor
+        // We finished adding the skeleton, so we record its named bones and remove the skeleton
+        // descriptor
+        commands.entity(root_id).insert(bones);
         commands.entity(entity).despawn();
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::{
+        construct_skeletons, update_skeleton_stats, BoneName, Skeleton, SkeletonBones,
+        SkeletonDescriptor, SkeletonError, SkeletonLimits, SkeletonStats,
+    };
+    use crate::Bone;
+    use bevy::{ecs::system::RunSystemOnce, prelude::*};
+    use std::f32::consts::PI;
+
+    // Test that the tip of a two-bone skeleton matches forward kinematics worked out by hand.
+    #[test]
+    fn test_bone_segments_two_bones() {
+        let mut world = World::new();
+        let skeleton = world
+            .spawn((Transform::from_xyz(1.0, 0.0, 0.0), Skeleton))
+            .id();
+        let upper = world.spawn(Bone::new(1.0, Vec2::ZERO)).id();
+        let lower = world.spawn(Bone::new(2.0, Vec2::new(0.0, PI / 2.0))).id();
+        world.entity_mut(skeleton).add_child(upper);
+        world.entity_mut(upper).add_child(lower);
+
+        // The first bone extends along the initial direction, and the second bends a quarter turn
+        // back towards the initial tangent
+        let segments = Skeleton::bone_segments(&world, skeleton);
+        assert_eq!(segments.len(), 2);
+        let (start, end) = segments[0];
+        assert!(start.distance(Vec3::new(1.0, 0.0, 0.0)) < 0.001);
+        assert!(end.distance(Vec3::new(2.0, 0.0, 0.0)) < 0.001);
+        let (start, end) = segments[1];
+        assert!(start.distance(Vec3::new(2.0, 0.0, 0.0)) < 0.001);
+        assert!(end.distance(Vec3::new(2.0, 2.0, 0.0)) < 0.001);
+    }
+
+    // Test that two named bones can be looked up by name once the skeleton has been constructed,
+    // and that unnamed bones are left out.
+    #[test]
+    fn test_named_bones() {
+        let root = SkeletonDescriptor::root;
+        let branch = SkeletonDescriptor::branch;
+        let leaf = SkeletonDescriptor::leaf;
+
+        let mut world = World::new();
+        world.spawn((
+            Transform::default(),
+            root(&[branch(
+                1.0,
+                [0.0, 0.0],
+                &[
+                    leaf(0.5, [0.0, 0.0]).with_name("hand"),
+                    leaf(0.5, [0.0, 0.0]),
+                ],
+            )
+            .with_name("arm")]),
+        ));
+        world.run_system_once(construct_skeletons);
+
+        let bones = world
+            .query_filtered::<&SkeletonBones, With<Skeleton>>()
+            .single(&world)
+            .clone();
+        assert_eq!(bones.iter().count(), 2);
+
+        let arm = bones.get("arm").unwrap();
+        let hand = bones.get("hand").unwrap();
+        assert_eq!(world.get::<BoneName>(arm), Some(&BoneName("arm")));
+        assert_eq!(world.get::<BoneName>(hand), Some(&BoneName("hand")));
+        assert_eq!(world.get::<Bone>(arm).unwrap().length(), 1.0);
+        assert_eq!(world.get::<Parent>(hand).unwrap().get(), arm);
+        assert_eq!(bones.get("foot"), None);
+    }
+
+    // Test the stats of a skeleton with one branch holding two leaves, both from the system and
+    // computed directly.
+    #[test]
+    fn test_stats_branch_with_two_leaves() {
+        let root = SkeletonDescriptor::root;
+        let branch = SkeletonDescriptor::branch;
+        let leaf = SkeletonDescriptor::leaf;
+
+        let mut world = World::new();
+        world.spawn((
+            Transform::default(),
+            root(&[branch(
+                1.0,
+                [0.0, 0.0],
+                &[leaf(0.5, [0.0, 0.0]), leaf(0.25, [0.0, 0.0])],
+            )]),
+        ));
+        world.run_system_once(construct_skeletons);
+        world.run_system_once(update_skeleton_stats);
+
+        let (skeleton, stats) = world
+            .query_filtered::<(Entity, &SkeletonStats), With<Skeleton>>()
+            .single(&world);
+        let expected = SkeletonStats {
+            bone_count: 3,
+            max_depth: 2,
+            total_length: 1.75,
+        };
+        assert_eq!(*stats, expected);
+        assert_eq!(Skeleton::stats(&world, skeleton), expected);
+    }
+
+    // Test that blending two poses of the same skeleton blends each bone, and that poses of
+    // differently shaped skeletons cannot be blended.
+    #[test]
+    fn test_lerp_poses() {
+        let root = SkeletonDescriptor::root;
+        let branch = SkeletonDescriptor::branch;
+        let leaf = SkeletonDescriptor::leaf;
+
+        let rest = root(&[branch(1.0, [0.0, 0.0], &[leaf(0.5, [0.0, 0.0])])]);
+        let bent = root(&[branch(1.0, [0.0, 0.5], &[leaf(0.5, [0.0, 1.0])])]);
+        let halfway = rest.lerp(&bent, 0.5).unwrap();
+        let upper = &halfway.children[0];
+        assert!(upper.bone.angle().distance(Vec2::new(0.0, 0.25)) < 0.001);
+        let lower = &upper.children[0];
+        assert!(lower.bone.angle().distance(Vec2::new(0.0, 0.5)) < 0.001);
+        assert_eq!(lower.bone.length(), 0.5);
+
+        let other = root(&[leaf(1.0, [0.0, 0.0])]);
+        assert_eq!(rest.lerp(&other, 0.5), None);
+    }
+
+    // Test that a mirrored limb has its rotations about the parent negated, while its lengths, bends
+    // and child structure are unchanged.
+    #[test]
+    fn test_mirror_limb() {
+        let branch = SkeletonDescriptor::branch;
+        let leaf = SkeletonDescriptor::leaf;
+
+        let arm = branch(
+            1.0,
+            [0.4, 0.3],
+            &[leaf(0.5, [-0.2, 0.6]), leaf(0.25, [0.0, 1.0])],
+        )
+        .with_roll(0.1)
+        .with_name("arm");
+        let mirrored = SkeletonDescriptor::mirror_x(&arm);
+        assert_eq!(mirrored.bone.angle(), Vec2::new(-0.4, 0.3));
+        assert_eq!(mirrored.bone.roll(), -0.1);
+        assert_eq!(mirrored.name, None);
+        assert_eq!(mirrored.children.len(), 2);
+        for (original, copy) in arm.children.iter().zip(&mirrored.children) {
+            assert_eq!(copy.bone.length(), original.bone.length());
+            assert_eq!(copy.bone.angle().x, -original.bone.angle().x);
+            assert_eq!(copy.bone.angle().y, original.bone.angle().y);
+            assert!(copy.children.is_empty());
+        }
+
+        // Mirroring twice gives back the original bones
+        assert_eq!(SkeletonDescriptor::mirror_x(&mirrored).bone, arm.bone);
+
+        let hips = branch(0.5, [0.0, 0.0], std::slice::from_ref(&arm)).with_mirrored();
+        assert_eq!(hips.children, [arm, mirrored]);
+    }
+
+    // Test that a well-formed descriptor passes validation, and that each kind of degenerate bone
+    // is reported.
+    #[test]
+    fn test_validate_bones() {
+        let root = SkeletonDescriptor::root;
+        let branch = SkeletonDescriptor::branch;
+        let leaf = SkeletonDescriptor::leaf;
+        let limits = SkeletonLimits::default();
+
+        let valid = root(&[branch(1.0, [0.0, 0.5], &[leaf(0.5, [1.0, 0.0])])]);
+        assert_eq!(valid.validate(&limits), Ok(()));
+
+        let invalid = root(&[branch(1.0, [0.0, 0.0], &[leaf(0.0, [0.0, 0.0])])]);
+        assert_eq!(
+            invalid.validate(&limits),
+            Err(SkeletonError::NonPositiveLength(0.0))
+        );
+        let invalid = root(&[leaf(-1.0, [0.0, 0.0])]);
+        assert_eq!(
+            invalid.validate(&limits),
+            Err(SkeletonError::NonPositiveLength(-1.0))
+        );
+        let invalid = root(&[leaf(f32::INFINITY, [0.0, 0.0])]);
+        assert_eq!(
+            invalid.validate(&limits),
+            Err(SkeletonError::NonFiniteLength(f32::INFINITY))
+        );
+        let invalid = root(&[leaf(1.0, [f32::NAN, 0.0])]);
+        assert!(matches!(
+            invalid.validate(&limits),
+            Err(SkeletonError::NonFiniteAngle(..))
+        ));
+        let invalid = root(&[leaf(1.0, [0.0, 0.0]).with_roll(f32::NAN)]);
+        assert!(matches!(
+            invalid.validate(&limits),
+            Err(SkeletonError::NonFiniteAngle(..))
+        ));
+    }
+
+    // Test that the depth and bone count limits are enforced, and that an invalid descriptor is
+    // removed without constructing a skeleton.
+    #[test]
+    fn test_validate_limits() {
+        let root = SkeletonDescriptor::root;
+        let branch = SkeletonDescriptor::branch;
+        let leaf = SkeletonDescriptor::leaf;
+        let limits = SkeletonLimits {
+            max_depth: 2,
+            max_bones: 3,
+        };
+
+        let deep = root(&[branch(
+            1.0,
+            [0.0, 0.0],
+            &[branch(1.0, [0.0, 0.0], &[leaf(1.0, [0.0, 0.0])])],
+        )]);
+        assert_eq!(deep.validate(&limits), Err(SkeletonError::TooDeep(2)));
+        let wide = root(&[
+            leaf(1.0, [0.0, 0.0]),
+            leaf(1.0, [0.0, 0.0]),
+            leaf(1.0, [0.0, 0.0]),
+            leaf(1.0, [0.0, 0.0]),
+        ]);
+        assert_eq!(wide.validate(&limits), Err(SkeletonError::TooManyBones(3)));
+
+        let mut world = World::new();
+        world.insert_resource(limits);
+        let descriptor = world.spawn((Transform::default(), deep)).id();
+        world.run_system_once(construct_skeletons);
+        assert!(world.get_entity(descriptor).is_none());
+        assert_eq!(world.query::<&Skeleton>().iter(&world).count(), 0);
+        assert_eq!(world.query::<&Bone>().iter(&world).count(), 0);
+    }
+}
+
 /*
 // Iterator
 // --------
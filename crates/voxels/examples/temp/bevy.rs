@@ -29,7 +29,9 @@ fn setup(mut commands: Commands) {
     // The voxel store is empty, so we write to it. The new values are as
     // defined by the sampler function, and writes happen within the range of
     // -10 to 10 in all three spatial axes.
-    voxel_store.write(-10..10, -10..10, -10..10, Sampler);
+    voxel_store
+        .write(-10..=10, -10..=10, -10..=10, Sampler::new(|_| Voxel::solid(1)))
+        .expect("the write range should be valid");
 
     // We spawn a new entity with a name and a voxel store component
     commands.spawn((Name::new("Main Voxel Store"), voxel_store));
@@ -12,5 +12,7 @@ fn main() {
     // The voxel store is empty, so we write to it. The new values are as
     // defined by the sampler function, and writes happen within the range of
     // -10 to 10 in all three spatial axes.
-    voxel_store.write(-10..10, -10..10, -10..10, Sampler);
+    voxel_store
+        .write(-10..=10, -10..=10, -10..=10, Sampler::new(|_| Voxel::solid(1)))
+        .expect("the write range should be valid");
 }
@@ -109,7 +109,7 @@ fn load_chunks(
             let chunk_position = (transform.translation / 32.0).as_ivec3();
             let chunk_queue = vec![chunk_position];
 
-            commands.spawn((Transform::from_xyz(0.0, 0.0, 0.0).with_scale(Vec3::splat(32.0))))
+            commands.spawn((Transform::from_xyz(0.0, 0.0, 0.0).with_scale(Vec3::splat(32.0))));
             chunk_loader.just_spawned = false;
         }
     }
@@ -1,4 +1,4 @@
-use crate::MeshingChunk;
+use crate::{ChunkViewer, MeshingChunk};
 use bevy::color::palettes::css::RED;
 pub use bevy::prelude::*;
 #[cfg(feature = "bevy_console")]
@@ -36,7 +36,7 @@ fn draw_chunk_gizmos(
     }
 }
 
-fn draw_chunk_loader_gizmos(chunk_loaders) {
+fn draw_chunk_loader_gizmos(_chunk_loaders: Query<&Transform, With<ChunkViewer>>) {
     todo!()
 }
 
@@ -34,25 +34,50 @@
 //! A basic usage example is provided below.
 //!
 //! ```
+//! # use prockit_voxels::{Sampler, Voxel, VoxelStore};
 //! // Create an empty voxel store
 //! let mut voxel_store = VoxelStore::new();
 //!
 //! // The voxel store is empty, so we write to it. The new values are as
 //! // defined by the sampler function, and writes happen within the range of
-//! // -10 to 10 in all three spatial axes.
-//! voxel_store.write(-10..=10, -10..=10, -10..=10, Sampler);
+//! // -10 to 10 in all three spatial axes. Writes are rejected with an error if
+//! // a range is inverted or covers too many voxels.
+//! voxel_store
+//!     .write(-10..=10, -10..=10, -10..=10, Sampler::new(|_| Voxel::solid(1)))
+//!     .unwrap();
 //! ```
 //!
-//! The `bevy` feature flag can be enabled for this crate for basic integrations
+//! The `bevy` feature flag, which is enabled by default, adds basic integrations
 //! with the Bevy game engine. This includes basic info, events, and component
 //! declarations for `VoxelStore` structs.
 
 #![deny(missing_docs, rustdoc::all)]
 
-use bevy::prelude::*;
-
+#[cfg(feature = "bevy")]
 mod chunk;
+#[cfg(feature = "bevy")]
 mod gizmos;
+mod temp;
 mod voxel;
 
+#[cfg(feature = "bevy")]
 pub use chunk::{ChunkViewer, MeshingChunk};
+#[cfg(feature = "bevy")]
+pub use temp::bevy::{
+    ChunkNeighborhood, VoxelStoreDiagnosticsPlugin, VoxelStorePlugin, VoxelWritten,
+    ATTRIBUTE_MATERIAL, VOXEL_STORE_LEN,
+};
+pub use temp::{
+    chunk::Chunk,
+    coords::{
+        chunk_to_voxel, chunk_to_world, voxel_to_chunk, voxel_to_world, world_to_chunk,
+        world_to_voxel, CHUNK_NEIGHBORS,
+    },
+    region::IAabb,
+    sampler::Sampler,
+    voxel_store::{
+        MemoryReport, StoreVersion, VoxelHit, VoxelPatch, VoxelStore, VoxelStoreError,
+        DEFAULT_WRITE_LIMIT,
+    },
+};
+pub use voxel::{Voxel, VoxelKind, VoxelVisibility};
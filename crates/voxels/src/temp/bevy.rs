@@ -3,15 +3,27 @@
 
 //! The `bevy` module is only available when the `bevy` feature flag is enabled,
 //! and should encapsulate all integrations between this crate and the Bevy game
-//! engine. The primary integrations are `Component` implementations and the
-//! [`VoxelStoreDiagnosticsPlugin`], which provides diagnostics (such as number
-//! of populated chunks) for all [`VoxelStore`]s in the ECS.
+//! engine. The primary integrations are `Component` implementations, the
+//! [`VoxelStorePlugin`], which applies queued writes and reports them with
+//! [`VoxelWritten`] events, the [`VoxelStoreDiagnosticsPlugin`], which
+//! provides diagnostics (such as number of populated chunks) for all
+//! [`VoxelStore`]s in the ECS, and [`VoxelStore::mesh_dirty_chunks`], which
+//! builds a `Mesh` for each edited chunk.
 
-use crate::voxel_store::VoxelStore;
+use crate::{
+    temp::{chunk::Chunk, coords::CHUNK_NEIGHBORS, region::IAabb, voxel_store::VoxelStore},
+    voxel::{Voxel, VoxelKind, VoxelVisibility},
+};
 use bevy::{
     diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
     ecs::component::StorageType,
     prelude::*,
+    render::{
+        mesh::{Indices, MeshVertexAttribute, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+        render_resource::VertexFormat,
+    },
+    tasks::{ComputeTaskPool, TaskPool},
 };
 
 // Not using the Component derive macro because VoxelStore is external to this
@@ -21,6 +33,257 @@ impl Component for VoxelStore {
     const STORAGE_TYPE: StorageType = StorageType::Table;
 }
 
+/// The `VoxelWritten` event is sent by the [`VoxelStorePlugin`] for every
+/// queued write it applies to a [`VoxelStore`] component. Writes applied
+/// immediately with [`VoxelStore::write`] do not send this event.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct VoxelWritten {
+    /// The entity holding the `VoxelStore` that was written to.
+    pub entity: Entity,
+    /// The region of voxels covered by the write.
+    pub region: IAabb,
+}
+
+/// The `VoxelStorePlugin` applies the queued writes of every [`VoxelStore`] in
+/// the ECS once per frame, so that each voxel store is eventually consistent
+/// with the writes queued to it. A [`VoxelWritten`] event is sent for each
+/// applied write.
+pub struct VoxelStorePlugin;
+impl Plugin for VoxelStorePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<VoxelWritten>()
+            .add_systems(Update, apply_queued_writes);
+    }
+}
+
+// This is the only system registered by the `VoxelStorePlugin`. Voxel stores
+// without queued writes are skipped, so that they are not needlessly marked as
+// changed.
+fn apply_queued_writes(
+    mut voxel_stores: Query<(Entity, &mut VoxelStore)>,
+    mut events: EventWriter<VoxelWritten>,
+) {
+    for (entity, mut voxel_store) in &mut voxel_stores {
+        if voxel_store.has_queued_writes() {
+            for region in voxel_store.apply_queued_writes() {
+                events.send(VoxelWritten { entity, region });
+            }
+        }
+    }
+}
+
+impl VoxelStore {
+    /// Builds a `Mesh` for every chunk which has changed since the last call,
+    /// as found by [`VoxelStore::take_dirty_chunks`], and returns each with the
+    /// position of the voxel at the chunk's origin. Vertex positions are local
+    /// to that origin, with each voxel spanning one unit. Each voxel is meshed
+    /// as the [`VoxelKind`] returned for it by `kind`.
+    ///
+    /// Chunks are meshed in parallel on the `ComputeTaskPool`. Each chunk is
+    /// meshed within its [`ChunkNeighborhood`] in this store, so that no faces
+    /// are left inside the solid at chunk boundaries, and ambient occlusion
+    /// carries across them. The neighbors of an edited chunk are remeshed
+    /// along with it for the same reason.
+    pub fn mesh_dirty_chunks<V: VoxelKind>(
+        &mut self,
+        kind: impl Fn(Voxel) -> V + Sync,
+    ) -> Vec<(IVec3, Mesh)> {
+        let dirty_chunks = self.take_dirty_chunks();
+        let voxel_store = &*self;
+        let kind = &kind;
+        ComputeTaskPool::get_or_init(TaskPool::default).scope(|scope| {
+            for (origin, chunk) in dirty_chunks {
+                scope.spawn(async move {
+                    let chunk_position = origin.div_euclid(IVec3::splat(Chunk::WIDTH));
+                    let neighborhood = ChunkNeighborhood {
+                        center: &chunk,
+                        neighbors: CHUNK_NEIGHBORS
+                            .map(|offset| voxel_store.chunk(chunk_position + offset)),
+                    };
+                    (origin, neighborhood.mesh(kind))
+                });
+            }
+        })
+    }
+}
+
+/// The `ATTRIBUTE_MATERIAL` vertex attribute holds the material id of the voxel
+/// each vertex of a chunk mesh belongs to, as returned by
+/// [`VoxelKind::material`]. It is ignored by the standard material, but can be
+/// read by custom materials to shade each material differently.
+pub const ATTRIBUTE_MATERIAL: MeshVertexAttribute =
+    MeshVertexAttribute::new("Voxel_Material", 270_532_417, VertexFormat::Uint32);
+
+/// The `ChunkNeighborhood` struct holds a [`Chunk`] along with the six chunks
+/// sharing a face with it, so that it can be meshed without gaps or
+/// unnecessary faces at its boundaries. The neighbors are ordered -X, +X, -Y,
+/// +Y, -Z, then +Z, the same as [`CHUNK_NEIGHBORS`], and a missing neighbor is
+/// treated as empty.
+#[derive(Clone, Copy, Debug)]
+pub struct ChunkNeighborhood<'a> {
+    /// The chunk to be meshed.
+    pub center: &'a Chunk,
+    /// The chunks sharing a face with the center chunk, if they exist.
+    pub neighbors: [Option<&'a Chunk>; 6],
+}
+
+impl<'a> ChunkNeighborhood<'a> {
+    /// Creates a `ChunkNeighborhood` with no neighbors, so that every face on
+    /// the boundary of the chunk is meshed.
+    pub fn isolated(center: &'a Chunk) -> Self {
+        Self {
+            center,
+            neighbors: [None; 6],
+        }
+    }
+
+    /// Builds a `Mesh` with a quad for every visible face of a voxel in the
+    /// center chunk, including faces against voxels of the neighboring chunks.
+    /// Each voxel is meshed as the [`VoxelKind`] returned for it by `kind`, and
+    /// [`VoxelKind::face_visible`] decides which faces are visible. A closure
+    /// returning `bool` meshes the voxels for which it returns `true` as solid.
+    /// Vertex positions are local to the chunk's origin, with each voxel
+    /// spanning one unit.
+    ///
+    /// Each vertex is colored by its ambient occlusion, from the opaque voxels
+    /// touching its corner in front of the face. Voxels diagonal to the center
+    /// chunk are not part of the neighborhood, so they never occlude. The
+    /// material id of each vertex is stored in [`ATTRIBUTE_MATERIAL`].
+    pub fn mesh<V: VoxelKind>(&self, kind: impl Fn(Voxel) -> V) -> Mesh {
+        self.mesh_scaled(kind, 1.0, Vec3::ZERO)
+    }
+
+    /// Builds a `Mesh` in the same way as [`ChunkNeighborhood::mesh`], but with
+    /// each voxel spanning `voxel_size` units and the chunk's origin placed at
+    /// `origin`. This places chunks at coarser levels of detail at their
+    /// correct scale and offset in the world.
+    pub fn mesh_scaled<V: VoxelKind>(
+        &self,
+        kind: impl Fn(Voxel) -> V,
+        voxel_size: f32,
+        origin: Vec3,
+    ) -> Mesh {
+        let kind_of = &kind;
+        let kind = |local: IVec3| self.get(local).map(kind_of);
+        let opaque = |local: IVec3| {
+            kind(local).is_some_and(|voxel| voxel.visibility() == VoxelVisibility::Opaque)
+        };
+
+        let mut positions = Vec::new();
+        let mut normals = Vec::new();
+        let mut colors = Vec::new();
+        let mut materials = Vec::new();
+        let mut indices = Vec::new();
+
+        // A uniformly empty chunk has no faces, and neither does a uniformly
+        // opaque chunk enclosed by uniformly opaque neighbors, so both skip
+        // straight to an empty mesh
+        let uniform_visibility =
+            |chunk: &Chunk| chunk.is_uniform().map(|voxel| kind_of(voxel).visibility());
+        let hidden = match uniform_visibility(self.center) {
+            Some(VoxelVisibility::Empty) => true,
+            Some(VoxelVisibility::Opaque) => self.neighbors.iter().all(|neighbor| {
+                neighbor
+                    .is_some_and(|chunk| uniform_visibility(chunk) == Some(VoxelVisibility::Opaque))
+            }),
+            _ => false,
+        };
+        let width = if hidden { 0 } else { Chunk::WIDTH };
+        for z in 0..width {
+            for y in 0..width {
+                for x in 0..width {
+                    let local = IVec3::new(x, y, z);
+                    let Some(voxel) = kind(local) else {
+                        continue;
+                    };
+                    if voxel.visibility() == VoxelVisibility::Empty {
+                        continue;
+                    }
+                    for axis in 0..3 {
+                        for positive in [false, true] {
+                            let mut normal = IVec3::ZERO;
+                            normal[axis] = if positive { 1 } else { -1 };
+                            let front = local + normal;
+                            if kind(front).is_some_and(|neighbor| !voxel.face_visible(&neighbor)) {
+                                continue;
+                            }
+
+                            // The corners wind counter-clockwise when seen from
+                            // outside the face
+                            let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+                            let mut corners = [(0, 0), (1, 0), (1, 1), (0, 1)];
+                            if !positive {
+                                corners.reverse();
+                            }
+                            let start = positions.len() as u32;
+                            for (du, dv) in corners {
+                                let mut corner = local;
+                                corner[axis] += positive as i32;
+                                corner[u] += du;
+                                corner[v] += dv;
+                                positions.push((corner.as_vec3() * voxel_size + origin).to_array());
+                                normals.push(normal.as_vec3().to_array());
+
+                                // The voxels beside and diagonal to this corner,
+                                // in the layer in front of the face
+                                let (mut side_u, mut side_v) = (IVec3::ZERO, IVec3::ZERO);
+                                side_u[u] = du * 2 - 1;
+                                side_v[v] = dv * 2 - 1;
+                                let occluded = match (
+                                    opaque(front + side_u),
+                                    opaque(front + side_v),
+                                    opaque(front + side_u + side_v),
+                                ) {
+                                    (true, true, _) => 3,
+                                    (a, b, c) => a as u32 + b as u32 + c as u32,
+                                };
+                                let light = 1.0 - 0.25 * occluded as f32;
+                                colors.push([light, light, light, 1.0]);
+                                materials.push(voxel.material() as u32);
+                            }
+                            indices.extend([
+                                start,
+                                start + 1,
+                                start + 2,
+                                start,
+                                start + 2,
+                                start + 3,
+                            ]);
+                        }
+                    }
+                }
+            }
+        }
+
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+        .with_inserted_attribute(ATTRIBUTE_MATERIAL, materials)
+        .with_inserted_indices(Indices::U32(indices))
+    }
+
+    // Returns the voxel at the given position local to the center chunk, which
+    // may lie in one of the neighboring chunks. Returns `None` for positions in
+    // a missing neighbor, or outside the center chunk along more than one axis.
+    fn get(&self, local: IVec3) -> Option<Voxel> {
+        let below = local.cmplt(IVec3::ZERO);
+        let above = local.cmpge(IVec3::splat(Chunk::WIDTH));
+        let chunk = match (below | above).bitmask().count_ones() {
+            0 => self.center,
+            1 => {
+                let axis = (below | above).bitmask().trailing_zeros() as usize;
+                self.neighbors[axis * 2 + above.any() as usize]?
+            }
+            _ => return None,
+        };
+        Some(chunk.get(local.rem_euclid(IVec3::splat(Chunk::WIDTH)).as_uvec3()))
+    }
+}
+
 /// The `VOXEL_STORE_LEN` diagnostic path will store the total number of
 /// populated voxel chunks of all voxel stores in the ECS
 pub const VOXEL_STORE_LEN: DiagnosticPath = DiagnosticPath::const_new("voxel_store_len");
@@ -49,3 +312,239 @@ fn update_diagnostics(mut diagnostics: Diagnostics, voxel_stores: Query<&VoxelSt
         total_len as f64
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ChunkNeighborhood, VoxelStorePlugin, VoxelWritten, ATTRIBUTE_MATERIAL};
+    use crate::{
+        temp::{chunk::Chunk, region::IAabb, sampler::Sampler, voxel_store::VoxelStore},
+        voxel::{Voxel, VoxelKind, VoxelVisibility},
+    };
+    use bevy::{
+        prelude::*,
+        render::mesh::{MeshVertexAttribute, VertexAttributeValues},
+    };
+
+    // Returns a chunk with every voxel set to the given voxel.
+    fn filled(voxel: Voxel) -> Chunk {
+        let mut chunk = Chunk::new();
+        for x in 0..4 {
+            for y in 0..4 {
+                for z in 0..4 {
+                    chunk.set(UVec3::new(x, y, z), voxel);
+                }
+            }
+        }
+        chunk
+    }
+
+    // Returns the values of a mesh attribute holding three floats per vertex.
+    fn float32x3(mesh: &Mesh, attribute: MeshVertexAttribute) -> &[[f32; 3]] {
+        match mesh.attribute(attribute) {
+            Some(VertexAttributeValues::Float32x3(values)) => values,
+            _ => panic!("the attribute should hold three floats per vertex"),
+        }
+    }
+
+    // Test that a queued write is applied by the plugin during an update, and
+    // that a matching event is sent.
+    #[test]
+    fn test_queued_write_sends_event() {
+        let mut app = App::new();
+        app.add_plugins(VoxelStorePlugin);
+
+        let mut voxel_store = VoxelStore::new();
+        let sampler = Sampler::new(|_| Voxel::solid(1));
+        assert!(voxel_store
+            .queue_write(0..=3, 0..=3, 0..=3, sampler)
+            .is_ok());
+        let entity = app.world_mut().spawn(voxel_store).id();
+
+        app.update();
+
+        let events = app.world().resource::<Events<VoxelWritten>>();
+        let written: Vec<_> = events.get_reader().read(events).copied().collect();
+        assert_eq!(written.len(), 1);
+        assert_eq!(written[0].entity, entity);
+        assert_eq!(written[0].region, IAabb::new(IVec3::ZERO, IVec3::splat(3)));
+
+        let voxel_store = app.world().get::<VoxelStore>(entity).unwrap();
+        assert!(!voxel_store.has_queued_writes());
+        assert_eq!(voxel_store.len(), 1);
+    }
+
+    // Test that chunks of a store are meshed without faces at the seams between
+    // them, and that editing one chunk remeshes it along with its neighbors.
+    #[test]
+    fn test_mesh_dirty_chunks() {
+        let mut voxel_store = VoxelStore::new();
+        let sampler = Sampler::new(|_| Voxel::solid(1));
+        assert!(voxel_store.write(0..=11, 0..=3, 0..=3, sampler).is_ok());
+        let meshes = voxel_store.mesh_dirty_chunks(|voxel| !voxel.is_air());
+        assert_eq!(meshes.len(), 3);
+        let vertices: usize = meshes.iter().map(|(_, mesh)| mesh.count_vertices()).sum();
+        assert_eq!(vertices, (5 + 4 + 5) * 16 * 4);
+        assert!(voxel_store
+            .mesh_dirty_chunks(|voxel| !voxel.is_air())
+            .is_empty());
+
+        let sampler = Sampler::new(|_| Voxel::air());
+        assert!(voxel_store.write(5..=5, 1..=1, 1..=1, sampler).is_ok());
+        let meshes = voxel_store.mesh_dirty_chunks(|voxel| !voxel.is_air());
+        assert_eq!(meshes.len(), 3);
+
+        // The middle chunk has 16 faces on each of its four outer sides, and
+        // the hole inside it adds 6 more facing inwards
+        let (_, mesh) = meshes
+            .iter()
+            .find(|(origin, _)| *origin == IVec3::new(4, 0, 0))
+            .unwrap();
+        assert_eq!(mesh.count_vertices(), (4 * 16 + 6) * 4);
+        assert_eq!(mesh.indices().unwrap().len(), (4 * 16 + 6) * 6);
+    }
+
+    // Test that the boundary faces of a full chunk disappear where it meets a
+    // solid neighbor, and remain where the neighbor is missing or empty.
+    #[test]
+    fn test_mesh_with_neighbors() {
+        let solid = filled(Voxel::solid(1));
+        let air = Chunk::new();
+        let is_solid = |voxel: Voxel| !voxel.is_air();
+        let isolated = ChunkNeighborhood::isolated(&solid).mesh(is_solid);
+        assert_eq!(isolated.count_vertices(), 6 * 16 * 4);
+
+        let neighborhood = ChunkNeighborhood {
+            center: &solid,
+            neighbors: [None, Some(&solid), Some(&air), None, None, Some(&solid)],
+        };
+        let mesh = neighborhood.mesh(is_solid);
+        assert_eq!(mesh.count_vertices(), 4 * 16 * 4);
+        let normals = float32x3(&mesh, Mesh::ATTRIBUTE_NORMAL);
+        assert!(!normals.contains(&[1.0, 0.0, 0.0]));
+        assert!(!normals.contains(&[0.0, 0.0, 1.0]));
+        assert!(normals.contains(&[0.0, -1.0, 0.0]));
+    }
+
+    // Test that a uniformly empty chunk meshes to an empty mesh even beside
+    // solid neighbors, as does a solid chunk enclosed by solid neighbors.
+    #[test]
+    fn test_mesh_uniform_chunks() {
+        let solid = filled(Voxel::solid(1));
+        let air = Chunk::new();
+        let is_solid = |voxel: Voxel| !voxel.is_air();
+        let empty = ChunkNeighborhood {
+            center: &air,
+            neighbors: [Some(&solid); 6],
+        };
+        let mesh = empty.mesh(is_solid);
+        assert_eq!(mesh.count_vertices(), 0);
+        assert_eq!(mesh.indices().unwrap().len(), 0);
+
+        let enclosed = ChunkNeighborhood {
+            center: &solid,
+            neighbors: [Some(&solid); 6],
+        };
+        assert_eq!(enclosed.mesh(is_solid).count_vertices(), 0);
+
+        // A single missing neighbor leaves the faces on that side visible
+        let mut open = enclosed;
+        open.neighbors[0] = None;
+        assert_eq!(open.mesh(is_solid).count_vertices(), 16 * 4);
+    }
+
+    // Test that meshing a chunk at twice the voxel size doubles its bounding
+    // box, and that the origin offsets it.
+    #[test]
+    fn test_mesh_scaled() {
+        let solid = filled(Voxel::solid(1));
+        let neighborhood = ChunkNeighborhood::isolated(&solid);
+        let is_solid = |voxel: Voxel| !voxel.is_air();
+        let unit = neighborhood.mesh(is_solid).compute_aabb().unwrap();
+        let origin = Vec3::new(-8.0, 0.0, 16.0);
+        let scaled = neighborhood
+            .mesh_scaled(is_solid, 2.0, origin)
+            .compute_aabb()
+            .unwrap();
+        assert_eq!(scaled.half_extents, unit.half_extents * 2.0);
+        assert_eq!(scaled.min(), origin.into());
+        assert_eq!(Vec3::from(scaled.max()), origin + Vec3::splat(8.0));
+    }
+
+    // Test that the corners of a top face beside a taller voxel are occluded,
+    // while the corners away from it are fully lit.
+    #[test]
+    fn test_mesh_ambient_occlusion() {
+        let mut chunk = Chunk::new();
+        chunk.set(UVec3::ZERO, Voxel::solid(1));
+        chunk.set(UVec3::new(1, 1, 0), Voxel::solid(1));
+        let mesh = ChunkNeighborhood::isolated(&chunk).mesh(|voxel| !voxel.is_air());
+
+        let positions = float32x3(&mesh, Mesh::ATTRIBUTE_POSITION);
+        let normals = float32x3(&mesh, Mesh::ATTRIBUTE_NORMAL);
+        let Some(VertexAttributeValues::Float32x4(colors)) = mesh.attribute(Mesh::ATTRIBUTE_COLOR)
+        else {
+            panic!("the mesh should have vertex colors");
+        };
+        let top: Vec<_> = (0..positions.len())
+            .filter(|&index| normals[index] == [0.0, 1.0, 0.0] && positions[index][1] == 1.0)
+            .collect();
+        assert_eq!(top.len(), 4);
+        for index in top {
+            let expected = if positions[index][0] == 1.0 {
+                0.75
+            } else {
+                1.0
+            };
+            assert_eq!(colors[index][0], expected);
+        }
+    }
+
+    // Test that a chunk is meshed through a custom voxel type, where stone
+    // shows its faces against water, but water hides the faces between its own
+    // voxels and against stone.
+    #[test]
+    fn test_mesh_custom_voxel() {
+        #[derive(Clone, Copy)]
+        enum Block {
+            Air,
+            Stone,
+            Water,
+        }
+        impl VoxelKind for Block {
+            type MergeValue = u16;
+            fn visibility(&self) -> VoxelVisibility {
+                match self {
+                    Block::Air => VoxelVisibility::Empty,
+                    Block::Stone => VoxelVisibility::Opaque,
+                    Block::Water => VoxelVisibility::Translucent,
+                }
+            }
+            fn merge_value(&self) -> u16 {
+                self.material()
+            }
+            fn material(&self) -> u16 {
+                *self as u16
+            }
+        }
+
+        let mut chunk = Chunk::new();
+        chunk.set(UVec3::ZERO, Voxel::solid(1));
+        chunk.set(UVec3::new(1, 0, 0), Voxel::solid(2));
+        chunk.set(UVec3::new(2, 0, 0), Voxel::solid(2));
+        let mesh = ChunkNeighborhood::isolated(&chunk).mesh(|voxel| match voxel.material() {
+            _ if voxel.is_air() => Block::Air,
+            1 => Block::Stone,
+            _ => Block::Water,
+        });
+
+        // Stone keeps all 6 faces, the first water voxel loses its faces
+        // against stone and water, and the second loses its face against water
+        assert_eq!(mesh.count_vertices(), (6 + 4 + 5) * 4);
+        let Some(VertexAttributeValues::Uint32(materials)) = mesh.attribute(ATTRIBUTE_MATERIAL)
+        else {
+            panic!("the mesh should have material ids");
+        };
+        let stone = materials.iter().filter(|&&material| material == 1).count();
+        assert_eq!(stone, 6 * 4);
+    }
+}
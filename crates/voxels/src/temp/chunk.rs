@@ -11,15 +11,17 @@
 #![allow(dead_code)]
 
 use crate::voxel::Voxel;
-use ndshape::ConstPow2Shape3u32;
+use glam::UVec3;
+use ndshape::{ConstPow2Shape3u32, Shape};
 
 /// The `Chunk` struct is built as a compact storage element for a grid of 4^3
 /// voxels. Several `Chunk`s make up the space of voxel store. A `Chunk` may
 /// have children, such that a space that would normally be occupied by a voxel
 /// may be subdivided and occupied by another `Chunk`.
 ///
-/// Users of this crate should not interact with the `Chunk` type directly--this
-/// struct should only be for internal use.
+/// Users of this crate should not need to build or modify `Chunk`s
+/// directly--apart from reading the coarse chunks returned by
+/// [`VoxelStore::read_lod`], this struct is only for internal use.
 ///
 /// A chunk is limited to 64 (4^3) voxels because this allows for a `u64` type
 /// to act as a bitmask for those values which are considered active or children
@@ -32,8 +34,8 @@ use ndshape::ConstPow2Shape3u32;
 /// whether a child exists. The application must query the voxel store again for
 /// the child chunk, at the position and level of detail expected given the
 /// index of the child indicator.
-#[derive(Clone, Copy)]
-pub(crate) struct Chunk {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Chunk {
     active: u64,
     values: [Voxel; 64],
 }
@@ -42,10 +44,94 @@ impl Chunk {
     // This shape type lets us linearize and delinearize indices for the chunk.
     pub(crate) const SHAPE: ConstPow2Shape3u32<2, 2, 2> = ConstPow2Shape3u32::<2, 2, 2>;
 
+    // The number of voxels along each axis of a chunk.
+    pub(crate) const WIDTH: i32 = 4;
+
     pub(crate) fn new() -> Self {
         Self {
             active: 0,
-            values: [Voxel::default(); 64],
+            values: [Voxel::air(); 64],
+        }
+    }
+
+    /// Returns the voxel shared by every position in this chunk, or `None` if
+    /// the chunk holds more than one distinct voxel. A chunk with no active
+    /// voxels is uniformly air, which is found from the active bitmask alone,
+    /// as is a chunk which mixes air and solid voxels.
+    pub fn is_uniform(&self) -> Option<Voxel> {
+        match self.active {
+            0 => Some(Voxel::air()),
+            u64::MAX => {
+                let first = self.values[0];
+                self.values[1..]
+                    .iter()
+                    .all(|voxel| *voxel == first)
+                    .then_some(first)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the voxel at the given position local to this chunk. Each
+    /// component of the position must be less than 4.
+    pub fn get(&self, local: UVec3) -> Voxel {
+        self.values[Self::SHAPE.linearize(local.to_array()) as usize]
+    }
+
+    // Sets the voxel at the given position local to this chunk, and updates the
+    // active bitmask so that only solid voxels are marked active.
+    pub(crate) fn set(&mut self, local: UVec3, voxel: Voxel) {
+        let index = Self::SHAPE.linearize(local.to_array());
+        self.values[index as usize] = voxel;
+        if voxel.is_air() {
+            self.active &= !(1 << index);
+        } else {
+            self.active |= 1 << index;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Chunk;
+    use crate::voxel::Voxel;
+    use glam::UVec3;
+
+    // Returns a chunk with every voxel set to the given voxel.
+    fn filled(voxel: Voxel) -> Chunk {
+        let mut chunk = Chunk::new();
+        for x in 0..4 {
+            for y in 0..4 {
+                for z in 0..4 {
+                    chunk.set(UVec3::new(x, y, z), voxel);
+                }
+            }
         }
+        chunk
+    }
+
+    // Test that new chunks are uniformly air, and filled chunks are uniformly
+    // the voxel they were filled with.
+    #[test]
+    fn test_is_uniform() {
+        assert_eq!(Chunk::new().is_uniform(), Some(Voxel::air()));
+        assert_eq!(filled(Voxel::solid(3)).is_uniform(), Some(Voxel::solid(3)));
+    }
+
+    // Test that a single differing voxel breaks uniformity, whether it differs
+    // by being air or by its material.
+    #[test]
+    fn test_single_voxel_breaks_uniformity() {
+        let mut chunk = filled(Voxel::solid(3));
+        chunk.set(UVec3::new(1, 2, 3), Voxel::solid(4));
+        assert_eq!(chunk.is_uniform(), None);
+
+        let mut chunk = filled(Voxel::solid(3));
+        chunk.set(UVec3::new(3, 3, 3), Voxel::air());
+        assert_eq!(chunk.is_uniform(), None);
+
+        let mut chunk = Chunk::new();
+        chunk.set(UVec3::ZERO, Voxel::solid(1));
+        assert_eq!(chunk.is_uniform(), None);
     }
 }
@@ -0,0 +1,112 @@
+// ▄▀▀ ▄▀▄ ▄▀▄ █▀▄ █▀▄ ▄▀▀   █▀▄ ▄▀▀
+// ▀▄▄ ▀▄▀ ▀▄▀ █▀▄ █▄▀ ▄██ ▄ █▀▄ ▄██
+
+//! The `coords` module contains conversions between world positions, voxel
+//! positions, and the positions of [`Chunk`]s. All conversions floor towards
+//! negative infinity rather than truncating towards zero, so that positions
+//! just below zero land in the voxel or chunk below the origin, rather than
+//! sharing the voxel or chunk at the origin.
+
+use crate::temp::chunk::Chunk;
+use glam::{IVec3, UVec3, Vec3};
+
+/// The offsets from the position of a [`Chunk`] to the positions of the six
+/// chunks sharing a face with it, ordered -X, +X, -Y, +Y, -Z, then +Z.
+pub const CHUNK_NEIGHBORS: [IVec3; 6] = [
+    IVec3::NEG_X,
+    IVec3::X,
+    IVec3::NEG_Y,
+    IVec3::Y,
+    IVec3::NEG_Z,
+    IVec3::Z,
+];
+
+/// Returns the position of the voxel containing the given world position,
+/// where each voxel is a cube with sides of `voxel_size` world units and the
+/// voxel at the origin spans from zero to `voxel_size` on each axis.
+pub fn world_to_voxel(world: Vec3, voxel_size: f32) -> IVec3 {
+    (world / voxel_size).floor().as_ivec3()
+}
+
+/// Returns the world position of the corner of the given voxel with the
+/// smallest coordinates. This is the inverse of [`world_to_voxel`].
+pub fn voxel_to_world(voxel: IVec3, voxel_size: f32) -> Vec3 {
+    voxel.as_vec3() * voxel_size
+}
+
+/// Splits a voxel position into the position of the [`Chunk`] containing it,
+/// and the position of the voxel local to that chunk.
+pub fn voxel_to_chunk(voxel: IVec3) -> (IVec3, UVec3) {
+    let width = IVec3::splat(Chunk::WIDTH);
+    (voxel.div_euclid(width), voxel.rem_euclid(width).as_uvec3())
+}
+
+/// Returns the voxel position of a voxel local to a [`Chunk`]. This is the
+/// inverse of [`voxel_to_chunk`].
+pub fn chunk_to_voxel(chunk: IVec3, local: UVec3) -> IVec3 {
+    chunk * Chunk::WIDTH + local.as_ivec3()
+}
+
+/// Returns the position of the [`Chunk`] containing the given world position,
+/// and the position of the voxel containing it local to that chunk. Each chunk
+/// is a cube with sides of `chunk_size` world units, holding 4 voxels along
+/// each side.
+pub fn world_to_chunk(world: Vec3, chunk_size: f32) -> (IVec3, UVec3) {
+    voxel_to_chunk(world_to_voxel(world, chunk_size / Chunk::WIDTH as f32))
+}
+
+/// Returns the world position of the corner of a voxel local to a [`Chunk`]
+/// with the smallest coordinates, where each chunk is a cube with sides of
+/// `chunk_size` world units. This is the inverse of [`world_to_chunk`].
+pub fn chunk_to_world(chunk: IVec3, local: UVec3, chunk_size: f32) -> Vec3 {
+    voxel_to_world(
+        chunk_to_voxel(chunk, local),
+        chunk_size / Chunk::WIDTH as f32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{chunk_to_world, voxel_to_chunk, world_to_chunk, world_to_voxel};
+    use glam::{IVec3, UVec3, Vec3};
+
+    // Test that positions just below zero floor into the voxel below the
+    // origin, where truncation would have placed them in the voxel at it.
+    #[test]
+    fn test_world_to_voxel_straddling_origin() {
+        assert_eq!(world_to_voxel(Vec3::splat(0.5), 1.0), IVec3::ZERO);
+        assert_eq!(world_to_voxel(Vec3::splat(-0.5), 1.0), IVec3::NEG_ONE);
+        assert_eq!(
+            world_to_voxel(Vec3::new(-0.1, 0.1, -2.0), 0.5),
+            IVec3::new(-1, 0, -4)
+        );
+    }
+
+    // Test that negative voxels land in negative chunks, counting up from the
+    // far side of the chunk.
+    #[test]
+    fn test_voxel_to_chunk_straddling_origin() {
+        assert_eq!(voxel_to_chunk(IVec3::ZERO), (IVec3::ZERO, UVec3::ZERO));
+        assert_eq!(
+            voxel_to_chunk(IVec3::NEG_ONE),
+            (IVec3::NEG_ONE, UVec3::splat(3))
+        );
+        assert_eq!(
+            voxel_to_chunk(IVec3::new(-5, 4, 3)),
+            (IVec3::new(-2, 1, 0), UVec3::new(3, 0, 3))
+        );
+    }
+
+    // Test that converting a world position to a chunk and back gives the
+    // corner of the voxel containing it, on either side of the origin.
+    #[test]
+    fn test_world_to_chunk_round_trip() {
+        let (chunk, local) = world_to_chunk(Vec3::new(-0.1, 5.0, 33.0), 8.0);
+        assert_eq!(chunk, IVec3::new(-1, 0, 4));
+        assert_eq!(local, UVec3::new(3, 2, 0));
+        assert_eq!(
+            chunk_to_world(chunk, local, 8.0),
+            Vec3::new(-2.0, 4.0, 32.0)
+        );
+    }
+}
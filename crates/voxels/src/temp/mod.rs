@@ -0,0 +1,15 @@
+// █▄ ▄█ ▄▀▄ █▀▄   █▀▄ ▄▀▀
+// █ ▀ █ ▀▄▀ █▄▀ ▄ █▀▄ ▄██
+
+//! The `temp` module holds the [`VoxelStore`] and the modules it is built
+//! from. Their public items are re-exported from the crate root.
+//!
+//! [`VoxelStore`]: voxel_store::VoxelStore
+
+#[cfg(feature = "bevy")]
+pub mod bevy;
+pub mod chunk;
+pub mod coords;
+pub mod region;
+pub mod sampler;
+pub mod voxel_store;
@@ -0,0 +1,48 @@
+// █▀▄ ██▀ ▄▀  █ ▄▀▄ █▄ █   █▀▄ ▄▀▀
+// █▀▄ █▄▄ ▀▄█ █ ▀▄▀ █ ▀█ ▄ █▀▄ ▄██
+
+//! The `region` module encapsulates the [`IAabb`] struct, which describes a
+//! box of voxel positions within a [`VoxelStore`]. Regions are used to report
+//! which voxels were touched by a write, and which voxels are allocated.
+
+use glam::IVec3;
+use std::ops::RangeInclusive;
+
+/// The `IAabb` struct is an integer, axis-aligned bounding box of voxel
+/// positions. Both the `min` and `max` corners are inclusive, so a box where
+/// `min` equals `max` contains exactly one voxel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct IAabb {
+    /// The corner of the box with the smallest coordinates.
+    pub min: IVec3,
+    /// The corner of the box with the largest coordinates.
+    pub max: IVec3,
+}
+
+impl IAabb {
+    /// Creates a new `IAabb` spanning from `min` to `max`, inclusive.
+    pub fn new(min: IVec3, max: IVec3) -> Self {
+        Self { min, max }
+    }
+
+    /// Creates a new `IAabb` spanning the given inclusive ranges along each
+    /// axis.
+    pub fn from_ranges(
+        x: &RangeInclusive<i32>,
+        y: &RangeInclusive<i32>,
+        z: &RangeInclusive<i32>,
+    ) -> Self {
+        Self {
+            min: IVec3::new(*x.start(), *y.start(), *z.start()),
+            max: IVec3::new(*x.end(), *y.end(), *z.end()),
+        }
+    }
+
+    /// Returns the smallest `IAabb` containing both this box and `other`.
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+}
@@ -5,8 +5,28 @@
 //! methods. [`Sampler`]s are user created functions used to provide the data
 //! that gets written to the [`VoxelStore`] as it iterates over a writing range.
 
-/// The `Sampler` struct will grant structure for sampling from a spatial range,
-/// intended to be used by users of this crate with their own functions passed
-/// in to write their data into the voxel store. As of now, this functionality
-/// is unimplemented.
-pub struct Sampler;
+use crate::voxel::Voxel;
+use glam::IVec3;
+
+/// The `Sampler` struct grants structure for sampling from a spatial range. It
+/// wraps a function provided by the user of this crate, which is called once
+/// for every voxel position in a write range and returns the [`Voxel`] value
+/// that should be stored at that position.
+pub struct Sampler {
+    function: Box<dyn Fn(IVec3) -> Voxel + Send + Sync>,
+}
+
+impl Sampler {
+    /// Creates a new `Sampler` from the given function. The function takes the
+    /// position of a voxel and returns the value to store at that position.
+    pub fn new(function: impl Fn(IVec3) -> Voxel + Send + Sync + 'static) -> Self {
+        Self {
+            function: Box::new(function),
+        }
+    }
+
+    /// Calls the wrapped function for the voxel at the given position.
+    pub fn sample(&self, position: IVec3) -> Voxel {
+        (self.function)(position)
+    }
+}
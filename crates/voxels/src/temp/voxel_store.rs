@@ -6,16 +6,166 @@
 //! crate, and act as high-level spatial region which can be written to and read
 //! from, as well as including level-of-detail and raymarching functionality.
 
-use crate::{chunk::Chunk, sampler::Sampler};
-use std::{collections::HashMap, ops::Range};
+use crate::{
+    temp::{
+        chunk::Chunk,
+        coords::{voxel_to_chunk, CHUNK_NEIGHBORS},
+        region::IAabb,
+        sampler::Sampler,
+    },
+    voxel::Voxel,
+};
+use glam::{I64Vec3, IVec3, UVec3, Vec3};
+use ndshape::Shape;
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt, iter,
+    mem::size_of,
+    ops::RangeInclusive,
+    sync::Arc,
+};
 
-// Use bevy's version of glam if present
-#[cfg(feature = "bevy")]
-use bevy::prelude::IVec3;
+/// The default maximum number of voxels a single write to a [`VoxelStore`]
+/// may cover, equivalent to a cube of 256 voxels along each axis. See
+/// [`VoxelStore::with_write_limit`] to configure it.
+pub const DEFAULT_WRITE_LIMIT: u64 = 1 << 24;
 
-// Otherwise default to the most recent glam
-#[cfg(not(feature = "bevy"))]
-use glam::IVec3;
+/// The `VoxelStoreError` enum describes the reasons a write to a
+/// [`VoxelStore`] may be rejected. Writes are validated before any voxels are
+/// touched, so a rejected write leaves the `VoxelStore` unchanged.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VoxelStoreError {
+    /// The range given for the named axis ends before it starts.
+    InvertedRange {
+        /// The axis (`'x'`, `'y'` or `'z'`) the range was given for.
+        axis: char,
+        /// The start of the offending range.
+        start: i32,
+        /// The end of the offending range.
+        end: i32,
+    },
+    /// The write covers more voxels than the `VoxelStore` allows in a single
+    /// write.
+    RangeTooLarge {
+        /// The number of voxels covered by the write.
+        count: u64,
+        /// The write limit of the `VoxelStore`.
+        limit: u64,
+    },
+}
+
+impl fmt::Display for VoxelStoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvertedRange { axis, start, end } => {
+                write!(f, "inverted range on the {axis} axis: {start}..={end}")
+            }
+            Self::RangeTooLarge { count, limit } => write!(
+                f,
+                "write covers {count} voxels, which exceeds the limit of {limit}"
+            ),
+        }
+    }
+}
+
+impl Error for VoxelStoreError {}
+
+/// A `StoreVersion` identifies the state of a [`VoxelStore`] at a point in
+/// time. It is taken with [`VoxelStore::snapshot`], and later passed to
+/// [`VoxelStore::diff_since`] to find what has changed since. Versions only
+/// increase, and every write or applied patch increases the version.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StoreVersion(u64);
+
+/// A `VoxelPatch` holds the chunks of a [`VoxelStore`] which changed between
+/// two versions, as produced by [`VoxelStore::diff_since`]. Applying it to
+/// another store with [`VoxelStore::apply_patch`] brings those chunks up to
+/// date, which allows edits to be sent over the network without sending the
+/// whole store.
+///
+/// Patches hold whole chunks of 4^3 voxels rather than individual voxels, so
+/// a single edited voxel costs a single chunk.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VoxelPatch {
+    version: StoreVersion,
+    chunks: Vec<(IVec3, Chunk)>,
+}
+
+impl VoxelPatch {
+    /// Returns the version of the store the patch was taken from.
+    pub fn version(&self) -> StoreVersion {
+        self.version
+    }
+
+    /// Returns the number of chunks in the patch.
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Returns whether the patch holds no changes.
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Returns an iterator over the chunks of the patch, along with the
+    /// position of the voxel at each chunk's origin, in the same way as
+    /// [`VoxelStore::iter_chunks`].
+    pub fn chunks(&self) -> impl Iterator<Item = (IVec3, &Chunk)> {
+        self.chunks
+            .iter()
+            .map(|(chunk_position, chunk)| (*chunk_position * Chunk::WIDTH, chunk))
+    }
+}
+
+/// The `MemoryReport` struct describes how much memory a [`VoxelStore`]
+/// occupies, as returned by [`VoxelStore::memory_usage`]. It is an estimate:
+/// it counts the memory the store has allocated for chunks and for the hash
+/// maps indexing them, but not allocator overhead or queued writes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryReport {
+    /// The number of allocated chunks, across all levels of detail.
+    pub chunk_count: usize,
+    /// The number of bytes occupied by the voxel data of allocated chunks.
+    pub voxel_bytes: usize,
+    /// The number of bytes occupied by the sparse index of chunks, including
+    /// the per-chunk versions used for diffing. Hash maps allocate ahead of
+    /// their contents, so this counts their capacity rather than their length.
+    pub index_bytes: usize,
+}
+
+impl MemoryReport {
+    /// Returns the total number of bytes in the report.
+    pub fn total_bytes(&self) -> usize {
+        self.voxel_bytes + self.index_bytes
+    }
+}
+
+/// The `VoxelHit` struct describes the first solid voxel found along a ray by
+/// [`VoxelStore::raycast`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VoxelHit {
+    /// The position of the voxel which was hit.
+    pub position: IVec3,
+    /// The value of the voxel which was hit.
+    pub voxel: Voxel,
+    /// The normal of the face the ray entered the voxel through, pointing
+    /// back towards the ray's origin. This is zero if the ray starts inside
+    /// the voxel.
+    pub normal: IVec3,
+    /// The distance along the ray at which it entered the voxel.
+    pub distance: f32,
+}
+
+// A write which has been validated and queued, but not yet applied to the
+// voxel store. If the write has a mask, only the positions it accepts are
+// written, and every other voxel in the region is left as it was.
+struct QueuedWrite {
+    region: IAabb,
+    sampler: Sampler,
+    mask: Option<Box<dyn Fn(IVec3) -> bool + Send + Sync>>,
+}
 
 /// The `VoxelStore` is the primary exported struct of this crate. It can be
 /// interacted with as a generic spatial region where voxels can be read from
@@ -29,12 +179,34 @@ use glam::IVec3;
 /// to by a 3D vector representing its location.
 pub struct VoxelStore {
     levels: Vec<HashMap<IVec3, Chunk>>,
+    queue: Vec<QueuedWrite>,
+    write_limit: u64,
+    version: u64,
+    chunk_versions: HashMap<IVec3, u64>,
+    dirty_version: u64,
 }
 
 impl VoxelStore {
     /// Creates a new, empty `VoxelStore`.
     pub fn new() -> Self {
-        Self { levels: Vec::new() }
+        Self {
+            // The first level holds chunks at full detail
+            levels: vec![HashMap::new()],
+            queue: Vec::new(),
+            write_limit: DEFAULT_WRITE_LIMIT,
+            version: 0,
+            chunk_versions: HashMap::new(),
+            dirty_version: 0,
+        }
+    }
+
+    /// Returns this `VoxelStore` with the maximum number of voxels a single
+    /// write may cover set to `limit`. Writes covering more voxels are
+    /// rejected with [`VoxelStoreError::RangeTooLarge`]. Defaults to
+    /// [`DEFAULT_WRITE_LIMIT`].
+    pub fn with_write_limit(mut self, limit: u64) -> Self {
+        self.write_limit = limit;
+        self
     }
 
     /// Returns the total number of populated [`Chunk`]s present in this
@@ -49,11 +221,1020 @@ impl VoxelStore {
         total_len
     }
 
-    /// This function will provide an interface for writing to a `VoxelStore`,
-    /// but is currently not implmented. It will take a range to edit, as well
-    /// as a [`Sampler`] as a function for setting voxel values within that
-    /// range.
-    pub fn write(&mut self, _x: Range<i64>, _y: Range<i64>, _z: Range<i64>, _sampler: Sampler) {
-        unimplemented!()
+    /// Returns the current version of this `VoxelStore`, to be passed to
+    /// [`VoxelStore::diff_since`] later on.
+    pub fn snapshot(&self) -> StoreVersion {
+        StoreVersion(self.version)
+    }
+
+    /// Returns a [`VoxelPatch`] holding every chunk which has changed since the
+    /// given version was taken. Chunks are compared by the version at which they
+    /// were last written, so a chunk which was written and then restored to its
+    /// earlier contents is still included.
+    pub fn diff_since(&self, version: StoreVersion) -> VoxelPatch {
+        let chunks = self
+            .chunk_versions
+            .iter()
+            .filter(|(_, chunk_version)| **chunk_version > version.0)
+            .map(|(chunk_position, _)| (*chunk_position, self.levels[0][chunk_position]))
+            .collect();
+        VoxelPatch {
+            version: self.snapshot(),
+            chunks,
+        }
+    }
+
+    /// Applies a [`VoxelPatch`] taken from another `VoxelStore`, replacing each
+    /// chunk of this store which the patch holds. This counts as a write, so it
+    /// increases the version of this store.
+    pub fn apply_patch(&mut self, patch: VoxelPatch) {
+        self.version += 1;
+        for (chunk_position, chunk) in patch.chunks {
+            self.levels[0].insert(chunk_position, chunk);
+            self.chunk_versions.insert(chunk_position, self.version);
+        }
+    }
+
+    /// Returns a copy of every full-detail [`Chunk`] which has changed since
+    /// the last call to this method, along with the position of the voxel at
+    /// its origin. The first call returns every chunk ever written. This is
+    /// the incremental path for remeshing edited chunks, tracked separately
+    /// from the versions passed to [`VoxelStore::diff_since`].
+    ///
+    /// The allocated chunks sharing a face with a changed chunk are returned as
+    /// well, since the faces and ambient occlusion at their boundaries depend
+    /// on the changed chunk.
+    pub fn take_dirty_chunks(&mut self) -> Vec<(IVec3, Chunk)> {
+        let dirty_version = self.dirty_version;
+        self.dirty_version = self.version;
+        let dirty: HashSet<IVec3> = self
+            .chunk_versions
+            .iter()
+            .filter(|(_, chunk_version)| **chunk_version > dirty_version)
+            .flat_map(|(chunk_position, _)| {
+                iter::once(*chunk_position)
+                    .chain(CHUNK_NEIGHBORS.map(|offset| *chunk_position + offset))
+            })
+            .collect();
+        dirty
+            .into_iter()
+            .filter_map(|chunk_position| {
+                let chunk = self.chunk(chunk_position)?;
+                Some((chunk_position * Chunk::WIDTH, *chunk))
+            })
+            .collect()
+    }
+
+    /// Returns the full-detail [`Chunk`] at the given chunk position, or `None`
+    /// if it is unallocated. See [`voxel_to_chunk`] for finding the chunk
+    /// position of a voxel.
+    pub fn chunk(&self, chunk_position: IVec3) -> Option<&Chunk> {
+        self.levels[0].get(&chunk_position)
+    }
+
+    /// Returns an iterator over every allocated full-detail [`Chunk`] of this
+    /// `VoxelStore`, along with the position of the voxel at its origin (the
+    /// corner with the smallest coordinates). Unallocated space, which is
+    /// entirely air, is skipped. Chunks are yielded in no particular order.
+    pub fn iter_chunks(&self) -> impl Iterator<Item = (IVec3, &Chunk)> {
+        self.levels[0]
+            .iter()
+            .map(|(chunk_position, chunk)| (*chunk_position * Chunk::WIDTH, chunk))
+    }
+
+    /// Returns an iterator over every allocated full-detail [`Chunk`] of this
+    /// `VoxelStore` in the same way as [`VoxelStore::iter_chunks`], but with
+    /// mutable access to each chunk.
+    pub fn iter_chunks_mut(&mut self) -> impl Iterator<Item = (IVec3, &mut Chunk)> {
+        self.levels[0]
+            .iter_mut()
+            .map(|(chunk_position, chunk)| (*chunk_position * Chunk::WIDTH, chunk))
+    }
+
+    /// Returns an estimate of the memory occupied by this `VoxelStore`, for
+    /// budgeting. This takes time proportional to the number of levels of
+    /// detail, not the number of chunks.
+    pub fn memory_usage(&self) -> MemoryReport {
+        // Each hash map slot holds a key and value, plus one control byte. The
+        // chunks in occupied slots are counted as voxel data instead.
+        let slot_bytes = |key: usize, value: usize| key + value + 1;
+        let mut report = MemoryReport::default();
+        for level in &self.levels {
+            report.chunk_count += level.len();
+            report.voxel_bytes += level.len() * size_of::<Chunk>();
+            report.index_bytes += level.capacity()
+                * slot_bytes(size_of::<IVec3>(), size_of::<Chunk>())
+                - level.len() * size_of::<Chunk>();
+        }
+        report.index_bytes +=
+            self.chunk_versions.capacity() * slot_bytes(size_of::<IVec3>(), size_of::<u64>());
+        report
+    }
+
+    /// Returns the smallest region containing every allocated chunk of this
+    /// `VoxelStore`, or `None` if no chunks are allocated. Because whole chunks
+    /// are allocated at once, the bounds are aligned to chunk boundaries and may
+    /// include some air around the voxels that have been written.
+    pub fn bounds(&self) -> Option<IAabb> {
+        self.iter_chunks()
+            .map(|(origin, _)| IAabb::new(origin, origin + IVec3::splat(Chunk::WIDTH - 1)))
+            .reduce(|bounds, chunk_bounds| bounds.union(&chunk_bounds))
+    }
+
+    /// Intersects a ray with the [`bounds`](VoxelStore::bounds) of this
+    /// `VoxelStore`, returning the distances along the ray at which it enters
+    /// and exits the bounds. Each voxel occupies a unit cube extending from its
+    /// position in the positive direction of each axis. Distances are measured
+    /// in multiples of the length of `direction`, and if the ray starts inside
+    /// the bounds, the entry distance is zero.
+    ///
+    /// Returns `None` if the store is empty or the ray misses the bounds. A
+    /// raymarcher can use this to clip rays to the populated space before
+    /// marching through individual chunks.
+    pub fn ray_bounds_intersection(&self, origin: Vec3, direction: Vec3) -> Option<(f32, f32)> {
+        let bounds = self.bounds()?;
+
+        // Slab method: find where the ray crosses the pair of planes bounding
+        // each axis, then intersect those intervals
+        let inverse = direction.recip();
+        let near = (bounds.min.as_vec3() - origin) * inverse;
+        let far = ((bounds.max + IVec3::ONE).as_vec3() - origin) * inverse;
+        let entry = near.min(far).max_element().max(0.0);
+        let exit = near.max(far).min_element();
+
+        if entry <= exit {
+            Some((entry, exit))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the first solid voxel along a ray, within `max_distance` of its
+    /// origin. Each voxel occupies a unit cube extending from its position in
+    /// the positive direction of each axis, and distances are measured in
+    /// world units regardless of the length of `direction`. Returns `None` if
+    /// the ray misses, or if `direction` is zero.
+    ///
+    /// Voxels are visited one at a time by a DDA traversal, but unallocated
+    /// chunks and chunks whose occupancy bitmask is empty are skipped whole, so
+    /// the cost grows with the number of populated chunks along the ray.
+    pub fn raycast(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<VoxelHit> {
+        let direction = direction.try_normalize()?;
+        let (entry, exit) = self.ray_bounds_intersection(origin, direction)?;
+        let exit = exit.min(max_distance);
+        let step = IVec3::new(
+            axis_step(direction.x),
+            axis_step(direction.y),
+            axis_step(direction.z),
+        );
+        let delta = direction.recip().abs();
+
+        // Finds the voxel the ray is in at distance `t`, along with the distance
+        // at which it leaves that voxel along each axis. A point lying on a
+        // voxel boundary belongs to the voxel the ray is heading into. The ray
+        // entered through the face it crossed last, unless it started there.
+        let enter = |t: f32| {
+            let point = origin + direction * t;
+            let mut voxel = IVec3::ZERO;
+            let mut t_max = Vec3::INFINITY;
+            let mut normal = IVec3::ZERO;
+            let mut crossed = f32::NEG_INFINITY;
+            for axis in 0..3 {
+                voxel[axis] = if step[axis] < 0 {
+                    point[axis].ceil() as i32 - 1
+                } else {
+                    point[axis].floor() as i32
+                };
+                if step[axis] != 0 {
+                    let boundary = voxel[axis] + (step[axis] > 0) as i32;
+                    t_max[axis] = (boundary as f32 - origin[axis]) / direction[axis];
+                    let previous = t_max[axis] - delta[axis];
+                    if previous > crossed {
+                        crossed = previous;
+                        normal = IVec3::ZERO;
+                        normal[axis] = -step[axis];
+                    }
+                }
+            }
+            if crossed <= 0.0 {
+                normal = IVec3::ZERO;
+            }
+            (voxel, t_max, normal)
+        };
+
+        let mut t = entry;
+        let (mut voxel, mut t_max, mut normal) = enter(t);
+        while t <= exit {
+            let (chunk_position, local) = voxel_to_chunk(voxel);
+            let chunk = self.levels[0]
+                .get(&chunk_position)
+                .filter(|chunk| chunk.is_uniform() != Some(Voxel::air()));
+            if let Some(chunk) = chunk {
+                let value = chunk.get(local);
+                if !value.is_air() {
+                    return Some(VoxelHit {
+                        position: voxel,
+                        voxel: value,
+                        normal,
+                        distance: t,
+                    });
+                }
+
+                // Step into the neighboring voxel whose boundary is nearest
+                let axis = min_axis(t_max);
+                t = t_max[axis];
+                voxel[axis] += step[axis];
+                t_max[axis] += delta[axis];
+                normal = IVec3::ZERO;
+                normal[axis] = -step[axis];
+            } else {
+                // Skip to where the ray leaves this empty chunk
+                let chunk_min = chunk_position * Chunk::WIDTH;
+                let chunk_max = chunk_min + IVec3::splat(Chunk::WIDTH - 1);
+                let mut chunk_exit = Vec3::INFINITY;
+                for axis in 0..3 {
+                    if step[axis] != 0 {
+                        let boundary = chunk_min[axis] + Chunk::WIDTH * (step[axis] > 0) as i32;
+                        chunk_exit[axis] = (boundary as f32 - origin[axis]) / direction[axis];
+                    }
+                }
+                let axis = min_axis(chunk_exit);
+                t = chunk_exit[axis].max(t);
+
+                // The point at the exit distance may round to just inside this
+                // chunk, so the voxel is moved across the boundary directly,
+                // and only found from the point along the other axes
+                voxel = enter(t).0.clamp(chunk_min, chunk_max);
+                voxel[axis] = if step[axis] > 0 {
+                    chunk_max[axis] + 1
+                } else {
+                    chunk_min[axis] - 1
+                };
+                for axis in 0..3 {
+                    if step[axis] != 0 {
+                        let boundary = voxel[axis] + (step[axis] > 0) as i32;
+                        t_max[axis] = (boundary as f32 - origin[axis]) / direction[axis];
+                    }
+                }
+                normal = IVec3::ZERO;
+                normal[axis] = -step[axis];
+            }
+        }
+        None
+    }
+
+    /// Returns the voxel at the given position. Positions which have never been
+    /// written to hold air.
+    pub fn read(&self, position: IVec3) -> Voxel {
+        let (chunk_position, local) = voxel_to_chunk(position);
+        self.levels[0]
+            .get(&chunk_position)
+            .map_or(Voxel::air(), |chunk| chunk.get(local))
+    }
+
+    /// Reads a coarse, level-of-detail copy of the voxels in `region`, where each
+    /// voxel of the returned [`Chunk`] summarizes a cube of `2^level` voxels
+    /// along each axis. The chunk starts at `region.min`, so it covers up to
+    /// `4 * 2^level` voxels along each axis; voxels beyond `region.max` are left
+    /// out of the summary, and coarse voxels covering none of the region are
+    /// air. Level zero returns the voxels of the region unchanged.
+    ///
+    /// Each coarse voxel takes the average density of the voxels it covers,
+    /// counting air as zero density, so that partially filled space thins out
+    /// rather than vanishing or growing. Its material is the most common
+    /// material among the solid voxels it covers, with ties going to the
+    /// lowest material id, because material ids are labels which cannot be
+    /// averaged. If the average density rounds down to zero, the coarse voxel
+    /// is air.
+    ///
+    /// Every voxel covered by the chunk is read, so the cost grows with
+    /// `8^level`. Panics if `level` is 31 or greater.
+    pub fn read_lod(&self, region: IAabb, level: u32) -> Chunk {
+        assert!(level < 31, "level of detail {level} is too coarse");
+        // Cells may start or end beyond the range of `i32` when the region is
+        // near its limits, so their bounds are found in `i64` and clamped to
+        // the region before narrowing
+        let scale = 1i64 << level;
+        let mut chunk = Chunk::new();
+        for index in 0..Chunk::SHAPE.size() {
+            let local = UVec3::from(Chunk::SHAPE.delinearize(index));
+            let cell_min = region.min.as_i64vec3() + local.as_i64vec3() * scale;
+            if cell_min.cmpgt(region.max.as_i64vec3()).any() {
+                continue;
+            }
+            let cell_max = (cell_min + I64Vec3::splat(scale - 1)).min(region.max.as_i64vec3());
+            let cell = IAabb::new(cell_min.as_ivec3(), cell_max.as_ivec3());
+            chunk.set(local, self.reduce(cell));
+        }
+        chunk
+    }
+
+    /// Writes to every voxel within the given inclusive ranges, setting each
+    /// voxel to the value returned by the [`Sampler`] for its position. The
+    /// write is applied immediately.
+    ///
+    /// Returns an error, without writing anything, if any range is inverted or
+    /// if the ranges cover more voxels than the write limit allows.
+    pub fn write(
+        &mut self,
+        x: RangeInclusive<i32>,
+        y: RangeInclusive<i32>,
+        z: RangeInclusive<i32>,
+        sampler: Sampler,
+    ) -> Result<(), VoxelStoreError> {
+        self.validate(&x, &y, &z)?;
+        let region = IAabb::from_ranges(&x, &y, &z);
+        self.apply(QueuedWrite {
+            region,
+            sampler,
+            mask: None,
+        });
+        Ok(())
+    }
+
+    /// Validates a write in the same way as [`VoxelStore::write`], but rather
+    /// than applying it immediately, queues it to be applied by the next call
+    /// to [`VoxelStore::apply_queued_writes`]. This allows the voxel store to be
+    /// eventually consistent with the writes made to it, without writing
+    /// in the middle of a frame.
+    pub fn queue_write(
+        &mut self,
+        x: RangeInclusive<i32>,
+        y: RangeInclusive<i32>,
+        z: RangeInclusive<i32>,
+        sampler: Sampler,
+    ) -> Result<(), VoxelStoreError> {
+        self.validate(&x, &y, &z)?;
+        let region = IAabb::from_ranges(&x, &y, &z);
+        self.queue.push(QueuedWrite {
+            region,
+            sampler,
+            mask: None,
+        });
+        Ok(())
+    }
+
+    /// Returns whether any writes are waiting to be applied by
+    /// [`VoxelStore::apply_queued_writes`].
+    pub fn has_queued_writes(&self) -> bool {
+        !self.queue.is_empty()
+    }
+
+    /// Applies all queued writes in the order they were queued, and returns the
+    /// region covered by each applied write, in the same order.
+    pub fn apply_queued_writes(&mut self) -> Vec<IAabb> {
+        let mut regions = Vec::new();
+        for write in std::mem::take(&mut self.queue) {
+            regions.push(write.region);
+            self.apply(write);
+        }
+        regions
+    }
+
+    /// Queues a write setting every voxel in the region to the given voxel. As
+    /// with [`VoxelStore::queue_write`], the write is applied by the next call
+    /// to [`VoxelStore::apply_queued_writes`], and is rejected with an error if
+    /// the region covers more voxels than the write limit allows.
+    pub fn fill(&mut self, region: IAabb, voxel: Voxel) -> Result<(), VoxelStoreError> {
+        self.queue_masked(region, Sampler::new(move |_| voxel), None)
+    }
+
+    /// Queues a write setting every voxel in the region for which the predicate
+    /// returns `true` to air, leaving the other voxels unchanged. For example, a
+    /// predicate testing the distance from a point carves a sphere out of the
+    /// region. The write is queued and validated as with [`VoxelStore::fill`].
+    pub fn carve(
+        &mut self,
+        region: IAabb,
+        predicate: impl Fn(IVec3) -> bool + Send + Sync + 'static,
+    ) -> Result<(), VoxelStoreError> {
+        self.queue_masked(
+            region,
+            Sampler::new(|_| Voxel::air()),
+            Some(Box::new(predicate)),
+        )
+    }
+
+    /// Queues a write copying the solid voxels of another `VoxelStore` into
+    /// this one, such that the voxel at the origin of the other store lands at
+    /// `origin` in this one. Air in the other store leaves the voxels of this
+    /// store unchanged. The other store is copied when this is called, so later
+    /// changes to it are not stamped. The write is queued and validated as with
+    /// [`VoxelStore::fill`], covering the [`bounds`](VoxelStore::bounds) of the
+    /// other store.
+    pub fn stamp(&mut self, origin: IVec3, other: &VoxelStore) -> Result<(), VoxelStoreError> {
+        let Some(bounds) = other.bounds() else {
+            return Ok(());
+        };
+        let region = IAabb::new(bounds.min + origin, bounds.max + origin);
+
+        // The sampler and the mask each need a copy of the other store's chunks
+        let chunks = other.levels[0].clone();
+        let read = move |position: IVec3| {
+            let (chunk_position, local) = voxel_to_chunk(position - origin);
+            chunks
+                .get(&chunk_position)
+                .map_or(Voxel::air(), |chunk| chunk.get(local))
+        };
+        let read = Arc::new(read);
+        let mask_read = read.clone();
+        self.queue_masked(
+            region,
+            Sampler::new(move |position| read(position)),
+            Some(Box::new(move |position| !mask_read(position).is_air())),
+        )
+    }
+
+    // Validates and queues a write over the region, with an optional mask.
+    fn queue_masked(
+        &mut self,
+        region: IAabb,
+        sampler: Sampler,
+        mask: Option<Box<dyn Fn(IVec3) -> bool + Send + Sync>>,
+    ) -> Result<(), VoxelStoreError> {
+        let IAabb { min, max } = region;
+        self.validate(&(min.x..=max.x), &(min.y..=max.y), &(min.z..=max.z))?;
+        self.queue.push(QueuedWrite {
+            region,
+            sampler,
+            mask,
+        });
+        Ok(())
+    }
+
+    // Checks that the ranges are not inverted, and that they do not cover more
+    // voxels than the write limit.
+    fn validate(
+        &self,
+        x: &RangeInclusive<i32>,
+        y: &RangeInclusive<i32>,
+        z: &RangeInclusive<i32>,
+    ) -> Result<(), VoxelStoreError> {
+        let mut count: u64 = 1;
+        for (axis, range) in [('x', x), ('y', y), ('z', z)] {
+            let (start, end) = (*range.start(), *range.end());
+            if start > end {
+                return Err(VoxelStoreError::InvertedRange { axis, start, end });
+            }
+            // The widest possible range spans 2^32 voxels, so the count for all
+            // three axes could overflow and must saturate instead
+            count = count.saturating_mul((end as i64 - start as i64 + 1) as u64);
+        }
+        if count > self.write_limit {
+            return Err(VoxelStoreError::RangeTooLarge {
+                count,
+                limit: self.write_limit,
+            });
+        }
+        Ok(())
+    }
+
+    // Samples and stores every voxel covered by the write. Chunks are only
+    // allocated when a solid voxel is written to them.
+    fn apply(&mut self, write: QueuedWrite) {
+        self.version += 1;
+        let IAabb { min, max } = write.region;
+        for z in min.z..=max.z {
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    let position = IVec3::new(x, y, z);
+                    if write.mask.as_ref().is_some_and(|mask| !mask(position)) {
+                        continue;
+                    }
+                    let voxel = write.sampler.sample(position);
+                    let (chunk_position, local) = voxel_to_chunk(position);
+                    if voxel.is_air() {
+                        if let Some(chunk) = self.levels[0].get_mut(&chunk_position) {
+                            chunk.set(local, voxel);
+                            self.chunk_versions.insert(chunk_position, self.version);
+                        }
+                    } else {
+                        self.levels[0]
+                            .entry(chunk_position)
+                            .or_insert_with(Chunk::new)
+                            .set(local, voxel);
+                        self.chunk_versions.insert(chunk_position, self.version);
+                    }
+                }
+            }
+        }
+    }
+
+    // Summarizes the voxels in the region as a single voxel, with the average
+    // density and the most common solid material. An empty region, including
+    // one whose max lies below its min along any axis, is air.
+    // The region is visited a chunk at a time, so that unallocated and uniform
+    // chunks are counted at once rather than voxel by voxel.
+    fn reduce(&self, region: IAabb) -> Voxel {
+        let mut materials: HashMap<u16, u64> = HashMap::new();
+        let mut density_sum: u64 = 0;
+        let mut count: u64 = 0;
+        let mut tally = |voxel: Voxel, occurrences: u64| {
+            count += occurrences;
+            if !voxel.is_air() {
+                density_sum += voxel.density() as u64 * occurrences;
+                *materials.entry(voxel.material()).or_default() += occurrences;
+            }
+        };
+
+        let (min_chunk, _) = voxel_to_chunk(region.min);
+        let (max_chunk, _) = voxel_to_chunk(region.max);
+        for chunk_z in min_chunk.z..=max_chunk.z {
+            for chunk_y in min_chunk.y..=max_chunk.y {
+                for chunk_x in min_chunk.x..=max_chunk.x {
+                    let chunk_position = IVec3::new(chunk_x, chunk_y, chunk_z);
+                    let origin = chunk_position * Chunk::WIDTH;
+                    let min = region.min.max(origin);
+                    let max = region.max.min(origin + IVec3::splat(Chunk::WIDTH - 1));
+                    if max.cmplt(min).any() {
+                        continue;
+                    }
+                    let volume = (max - min + IVec3::ONE).as_uvec3();
+                    let volume = volume.x as u64 * volume.y as u64 * volume.z as u64;
+
+                    let Some(chunk) = self.levels[0].get(&chunk_position) else {
+                        tally(Voxel::air(), volume);
+                        continue;
+                    };
+                    if let Some(voxel) = chunk.is_uniform() {
+                        tally(voxel, volume);
+                        continue;
+                    }
+                    for z in min.z..=max.z {
+                        for y in min.y..=max.y {
+                            for x in min.x..=max.x {
+                                let local = (IVec3::new(x, y, z) - origin).as_uvec3();
+                                tally(chunk.get(local), 1);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let density = density_sum.checked_div(count).unwrap_or(0);
+        let material = materials
+            .into_iter()
+            .max_by_key(|&(material, occurrences)| (occurrences, Reverse(material)))
+            .map(|(material, _)| material);
+        match material {
+            Some(material) if density > 0 => Voxel::new(material, density as u8),
+            _ => Voxel::air(),
+        }
+    }
+}
+
+// Returns the direction a ray steps along an axis, given its direction's
+// component on that axis.
+fn axis_step(component: f32) -> i32 {
+    if component > 0.0 {
+        1
+    } else if component < 0.0 {
+        -1
+    } else {
+        0
+    }
+}
+
+// Returns the axis of the smallest component of the vector.
+fn min_axis(vector: Vec3) -> usize {
+    if vector.x <= vector.y && vector.x <= vector.z {
+        0
+    } else if vector.y <= vector.z {
+        1
+    } else {
+        2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{VoxelStore, VoxelStoreError};
+    use crate::{
+        temp::{chunk::Chunk, region::IAabb, sampler::Sampler},
+        voxel::Voxel,
+    };
+    use glam::{IVec3, UVec3, Vec3};
+
+    // Test that a range which ends before it starts is rejected.
+    #[test]
+    #[allow(clippy::reversed_empty_ranges)]
+    fn test_write_inverted_range() {
+        let mut voxel_store = VoxelStore::new();
+        let result = voxel_store.write(0..=1, 10..=-10, 0..=1, Sampler::new(|_| Voxel::solid(1)));
+        assert_eq!(
+            result,
+            Err(VoxelStoreError::InvertedRange {
+                axis: 'y',
+                start: 10,
+                end: -10
+            })
+        );
+        assert_eq!(voxel_store.len(), 0);
+    }
+
+    // Test that a write covering more voxels than the write limit is rejected,
+    // including when queued.
+    #[test]
+    fn test_write_over_limit() {
+        let mut voxel_store = VoxelStore::new().with_write_limit(1000);
+        let result =
+            voxel_store.queue_write(0..=10, 0..=9, 0..=9, Sampler::new(|_| Voxel::solid(1)));
+        assert_eq!(
+            result,
+            Err(VoxelStoreError::RangeTooLarge {
+                count: 1100,
+                limit: 1000
+            })
+        );
+    }
+
+    // Test that a small write stores the sampled values, spanning chunks on both
+    // sides of the origin.
+    #[test]
+    fn test_write_small_range() {
+        let mut voxel_store = VoxelStore::new();
+        let sampler = Sampler::new(|position: IVec3| Voxel::solid((position.x + 10) as u16));
+        assert!(voxel_store.write(-2..=1, 0..=0, 0..=0, sampler).is_ok());
+
+        // Positions -2 and -1 sit in a different chunk from positions 0 and 1
+        assert_eq!(voxel_store.len(), 2);
+        assert_eq!(voxel_store.read(IVec3::new(-2, 0, 0)), Voxel::solid(8));
+        assert_eq!(voxel_store.read(IVec3::new(1, 0, 0)), Voxel::solid(11));
+        assert_eq!(voxel_store.read(IVec3::new(2, 0, 0)), Voxel::air());
+    }
+
+    // Test that materials and densities are read back exactly as they were
+    // sampled, and that writing air leaves no chunk allocated.
+    #[test]
+    fn test_material_round_trip() {
+        let mut voxel_store = VoxelStore::new();
+        let sampler = Sampler::new(|position: IVec3| match position.x {
+            0 => Voxel::solid(300),
+            1 => Voxel::new(301, 64),
+            _ => Voxel::air(),
+        });
+        assert!(voxel_store.write(0..=2, 0..=0, 0..=0, sampler).is_ok());
+        assert_eq!(voxel_store.read(IVec3::new(0, 0, 0)), Voxel::solid(300));
+        assert_eq!(voxel_store.read(IVec3::new(1, 0, 0)), Voxel::new(301, 64));
+        assert!(voxel_store.read(IVec3::new(2, 0, 0)).is_air());
+
+        let mut empty_store = VoxelStore::new();
+        let sampler = Sampler::new(|_| Voxel::air());
+        assert!(empty_store.write(0..=8, 0..=8, 0..=8, sampler).is_ok());
+        assert_eq!(empty_store.len(), 0);
+    }
+
+    // Test the bounds of a store holding a cube of two chunks along each axis,
+    // and rays which pass through and miss those bounds.
+    #[test]
+    fn test_ray_bounds_intersection() {
+        let mut voxel_store = VoxelStore::new();
+        assert_eq!(voxel_store.bounds(), None);
+        assert_eq!(
+            voxel_store.ray_bounds_intersection(Vec3::ZERO, Vec3::X),
+            None
+        );
+
+        let sampler = Sampler::new(|_| Voxel::solid(1));
+        assert!(voxel_store.write(0..=7, 0..=7, 0..=7, sampler).is_ok());
+        assert_eq!(
+            voxel_store.bounds(),
+            Some(IAabb::new(IVec3::ZERO, IVec3::splat(7)))
+        );
+
+        // The bounds span from 0 to 8 along each axis
+        let hit = voxel_store.ray_bounds_intersection(Vec3::new(-2.0, 4.0, 4.0), Vec3::X);
+        assert_eq!(hit, Some((2.0, 10.0)));
+
+        // A ray starting inside the bounds enters immediately
+        let inside = voxel_store.ray_bounds_intersection(Vec3::splat(4.0), Vec3::NEG_Y);
+        assert_eq!(inside, Some((0.0, 4.0)));
+
+        let miss = voxel_store.ray_bounds_intersection(Vec3::new(-2.0, 20.0, 4.0), Vec3::X);
+        assert_eq!(miss, None);
+    }
+
+    // Test that level one halves the resolution, so that a split down the
+    // middle of an 8^3 region lands on the middle of the coarse chunk.
+    #[test]
+    fn test_read_lod_halves_resolution() {
+        let mut voxel_store = VoxelStore::new();
+        let sampler =
+            Sampler::new(|position: IVec3| Voxel::solid(if position.x < 4 { 1 } else { 2 }));
+        assert!(voxel_store.write(0..=7, 0..=7, 0..=7, sampler).is_ok());
+
+        let region = IAabb::new(IVec3::ZERO, IVec3::splat(7));
+        let chunk = voxel_store.read_lod(region, 1);
+        assert_eq!(chunk.get(UVec3::new(1, 3, 0)), Voxel::solid(1));
+        assert_eq!(chunk.get(UVec3::new(2, 0, 3)), Voxel::solid(2));
+
+        // Level zero copies the region voxel for voxel
+        let chunk = voxel_store.read_lod(region, 0);
+        assert_eq!(chunk.get(UVec3::new(3, 0, 0)), Voxel::solid(1));
+    }
+
+    // Test that a coarse voxel keeps the dominant material of the voxels it
+    // covers, and averages their density with the surrounding air.
+    #[test]
+    fn test_read_lod_dominant_material() {
+        let mut voxel_store = VoxelStore::new();
+        let sampler = Sampler::new(|position: IVec3| match position.y {
+            0 if position.x == 0 => Voxel::solid(4),
+            0 => Voxel::solid(3),
+            _ => Voxel::air(),
+        });
+        assert!(voxel_store.write(0..=1, 0..=1, 0..=1, sampler).is_ok());
+
+        let region = IAabb::new(IVec3::ZERO, IVec3::splat(1));
+        let chunk = voxel_store.read_lod(region, 1);
+        assert_eq!(chunk.get(UVec3::ZERO), Voxel::new(3, u8::MAX / 2));
+
+        // Coarse voxels beyond the region are air
+        assert!(chunk.get(UVec3::new(1, 0, 0)).is_air());
+    }
+
+    // Test that coarse voxels past the end of the region are air, even when
+    // the chunk they start in is uniformly solid.
+    #[test]
+    fn test_read_lod_beyond_uniform_chunk() {
+        let mut voxel_store = VoxelStore::new();
+        let sampler = Sampler::new(|_| Voxel::solid(1));
+        assert!(voxel_store.write(0..=3, 0..=3, 0..=3, sampler).is_ok());
+
+        let chunk = voxel_store.read_lod(IAabb::new(IVec3::ZERO, IVec3::ZERO), 1);
+        assert_eq!(chunk.get(UVec3::ZERO), Voxel::solid(1));
+        assert!(chunk.get(UVec3::new(1, 0, 0)).is_air());
+        assert!(chunk.get(UVec3::new(0, 1, 1)).is_air());
+    }
+
+    // Test that the coarsest allowed level reads a region at the very end of
+    // the voxel coordinate range, where the cells reach past `i32::MAX`.
+    #[test]
+    fn test_read_lod_coarsest_level() {
+        let mut voxel_store = VoxelStore::new();
+        let sampler = Sampler::new(|_| Voxel::solid(1));
+        let end = i32::MAX;
+        assert!(voxel_store
+            .write(end - 3..=end, end - 3..=end, end - 3..=end, sampler)
+            .is_ok());
+
+        let region = IAabb::new(IVec3::splat(end - 3), IVec3::MAX);
+        let chunk = voxel_store.read_lod(region, 30);
+        assert_eq!(chunk.get(UVec3::ZERO), Voxel::solid(1));
+        assert!(chunk.get(UVec3::new(1, 0, 0)).is_air());
+        assert!(chunk.get(UVec3::splat(3)).is_air());
+    }
+
+    // Test that two disjoint cubes yield only the chunks they touch, at the
+    // origins of those chunks.
+    #[test]
+    fn test_iter_chunks() {
+        let mut voxel_store = VoxelStore::new();
+        let sampler = Sampler::new(|_| Voxel::solid(1));
+        assert!(voxel_store.write(0..=3, 0..=3, 0..=3, sampler).is_ok());
+        let sampler = Sampler::new(|_| Voxel::solid(2));
+        assert!(voxel_store.write(-8..=-5, 16..=19, 0..=7, sampler).is_ok());
+
+        let mut origins: Vec<IVec3> = voxel_store
+            .iter_chunks()
+            .map(|(origin, _)| origin)
+            .collect();
+        origins.sort_by_key(|origin| origin.to_array());
+        assert_eq!(
+            origins,
+            vec![
+                IVec3::new(-8, 16, 0),
+                IVec3::new(-8, 16, 4),
+                IVec3::new(0, 0, 0),
+            ]
+        );
+
+        for (origin, chunk) in voxel_store.iter_chunks_mut() {
+            let material = if origin.x < 0 { 2 } else { 1 };
+            assert_eq!(chunk.get(UVec3::ZERO), Voxel::solid(material));
+        }
+    }
+
+    // Test that a patch of the edits made since a snapshot brings a store
+    // holding the pre-edit contents up to date.
+    #[test]
+    fn test_diff_and_apply_patch() {
+        let mut voxel_store = VoxelStore::new();
+        let mut replica = VoxelStore::new();
+        for store in [&mut voxel_store, &mut replica] {
+            let sampler = Sampler::new(|_| Voxel::solid(1));
+            assert!(store.write(0..=7, 0..=3, 0..=3, sampler).is_ok());
+        }
+        let version = voxel_store.snapshot();
+
+        // Edit one existing chunk, and allocate one new chunk
+        let sampler = Sampler::new(|_| Voxel::air());
+        assert!(voxel_store.write(1..=1, 1..=1, 1..=1, sampler).is_ok());
+        let sampler = Sampler::new(|_| Voxel::solid(2));
+        assert!(voxel_store.write(-4..=-4, 0..=0, 0..=0, sampler).is_ok());
+        assert!(voxel_store.snapshot() > version);
+
+        let patch = voxel_store.diff_since(version);
+        assert_eq!(patch.len(), 2);
+        replica.apply_patch(patch);
+
+        let chunks = |store: &VoxelStore| {
+            let mut chunks: Vec<(IVec3, Chunk)> = store
+                .iter_chunks()
+                .map(|(origin, chunk)| (origin, *chunk))
+                .collect();
+            chunks.sort_by_key(|(origin, _)| origin.to_array());
+            chunks
+        };
+        assert_eq!(chunks(&replica), chunks(&voxel_store));
+        assert!(voxel_store.diff_since(voxel_store.snapshot()).is_empty());
+    }
+
+    // Test that the reported voxel bytes grow with the number of chunks
+    // written, and that an empty store reports no voxel data.
+    #[test]
+    fn test_memory_usage_scales_with_chunks() {
+        let mut voxel_store = VoxelStore::new();
+        assert_eq!(voxel_store.memory_usage().voxel_bytes, 0);
+
+        let sampler = Sampler::new(|_| Voxel::solid(1));
+        assert!(voxel_store.write(0..=3, 0..=3, 0..=3, sampler).is_ok());
+        let one_chunk = voxel_store.memory_usage();
+        assert_eq!(one_chunk.chunk_count, 1);
+
+        let sampler = Sampler::new(|_| Voxel::solid(1));
+        assert!(voxel_store.write(0..=15, 0..=3, 0..=3, sampler).is_ok());
+        let four_chunks = voxel_store.memory_usage();
+        assert_eq!(four_chunks.chunk_count, 4);
+        assert_eq!(four_chunks.voxel_bytes, 4 * one_chunk.voxel_bytes);
+        assert!(four_chunks.total_bytes() > one_chunk.total_bytes());
+    }
+
+    // Test filling a cube and carving a sphere out of its middle, which only
+    // takes effect once the queued writes are applied.
+    #[test]
+    fn test_fill_and_carve() {
+        let mut voxel_store = VoxelStore::new();
+        let cube = IAabb::new(IVec3::ZERO, IVec3::splat(7));
+        assert!(voxel_store.fill(cube, Voxel::solid(1)).is_ok());
+        assert!(voxel_store.read(IVec3::ZERO).is_air());
+
+        let center = Vec3::splat(3.5);
+        assert!(voxel_store
+            .carve(cube, move |position| position.as_vec3().distance(center)
+                < 3.0)
+            .is_ok());
+        assert_eq!(voxel_store.apply_queued_writes(), vec![cube, cube]);
+
+        // The middle of the cube is hollow, while its corners and faces remain
+        assert!(voxel_store.read(IVec3::splat(3)).is_air());
+        assert!(voxel_store.read(IVec3::new(1, 3, 4)).is_air());
+        assert_eq!(voxel_store.read(IVec3::ZERO), Voxel::solid(1));
+        assert_eq!(voxel_store.read(IVec3::new(0, 3, 3)), Voxel::solid(1));
+        assert_eq!(voxel_store.read(IVec3::splat(7)), Voxel::solid(1));
+    }
+
+    // Test that stamping a small store into a larger one copies its solid
+    // voxels to the offset position, without copying its air over.
+    #[test]
+    fn test_stamp() {
+        let mut stamp = VoxelStore::new();
+        let sampler = Sampler::new(|position: IVec3| match position {
+            IVec3 { x: 0, y: 0, z: 0 } => Voxel::air(),
+            _ => Voxel::solid(2),
+        });
+        assert!(stamp.write(0..=1, 0..=1, 0..=1, sampler).is_ok());
+
+        let mut voxel_store = VoxelStore::new();
+        let sampler = Sampler::new(|_| Voxel::solid(1));
+        assert!(voxel_store.write(0..=15, 0..=0, 0..=15, sampler).is_ok());
+        assert!(voxel_store.stamp(IVec3::new(10, 0, -3), &stamp).is_ok());
+        voxel_store.apply_queued_writes();
+
+        // Air in the stamp leaves the ground below it
+        assert_eq!(voxel_store.read(IVec3::new(10, 0, -3)), Voxel::air());
+        assert_eq!(voxel_store.read(IVec3::new(10, 0, 0)), Voxel::solid(1));
+        assert_eq!(voxel_store.read(IVec3::new(11, 0, -3)), Voxel::solid(2));
+        assert_eq!(voxel_store.read(IVec3::new(11, 1, -2)), Voxel::solid(2));
+        assert!(voxel_store.read(IVec3::new(12, 1, -2)).is_air());
+
+        // Stamping an empty store queues nothing
+        assert!(voxel_store.stamp(IVec3::ZERO, &VoxelStore::new()).is_ok());
+        assert!(!voxel_store.has_queued_writes());
+    }
+
+    // Test that a ray finds a solid voxel beyond an empty chunk, reporting the
+    // face it entered through, and that a ray starting inside a voxel hits it
+    // immediately.
+    #[test]
+    fn test_raycast_hit() {
+        let mut voxel_store = VoxelStore::new();
+        let sampler = |position: IVec3| Voxel::solid(position.x as u16);
+        assert!(voxel_store
+            .write(0..=0, 3..=3, 3..=3, Sampler::new(sampler))
+            .is_ok());
+        assert!(voxel_store
+            .write(9..=9, 0..=0, 0..=0, Sampler::new(sampler))
+            .is_ok());
+
+        let origin = Vec3::new(-2.0, 0.5, 0.5);
+        let hit = voxel_store.raycast(origin, Vec3::X * 3.0, 100.0).unwrap();
+        assert_eq!(hit.position, IVec3::new(9, 0, 0));
+        assert_eq!(hit.voxel, Voxel::solid(9));
+        assert_eq!(hit.normal, IVec3::NEG_X);
+        assert_eq!(hit.distance, 11.0);
+
+        // Coming down onto the voxel from above
+        let hit = voxel_store
+            .raycast(Vec3::new(0.5, 10.0, 3.5), Vec3::NEG_Y, 100.0)
+            .unwrap();
+        assert_eq!(hit.position, IVec3::new(0, 3, 3));
+        assert_eq!(hit.normal, IVec3::Y);
+
+        let hit = voxel_store
+            .raycast(Vec3::new(9.5, 0.5, 0.5), Vec3::ONE, 100.0)
+            .unwrap();
+        assert_eq!((hit.normal, hit.distance), (IVec3::ZERO, 0.0));
+    }
+
+    // Test that rays which only pass through empty space, leave the store, or
+    // run out of distance miss.
+    #[test]
+    fn test_raycast_miss() {
+        let mut voxel_store = VoxelStore::new();
+        assert_eq!(voxel_store.raycast(Vec3::ZERO, Vec3::X, 100.0), None);
+
+        let sampler = Sampler::new(|_| Voxel::solid(1));
+        assert!(voxel_store.write(9..=9, 0..=0, 0..=0, sampler).is_ok());
+        let origin = Vec3::new(-2.0, 0.5, 0.5);
+        assert_eq!(voxel_store.raycast(origin, Vec3::NEG_X, 100.0), None);
+        assert_eq!(voxel_store.raycast(origin, Vec3::X, 10.0), None);
+        assert_eq!(voxel_store.raycast(origin, Vec3::ZERO, 100.0), None);
+        let above = Vec3::new(-2.0, 1.5, 0.5);
+        assert_eq!(voxel_store.raycast(above, Vec3::X, 100.0), None);
+    }
+
+    // Test that a ray sliding along the top face of a floor does not hit it,
+    // while a ray just below the face hits the side of the floor. A pillar in
+    // the far corner keeps the face inside the bounds of the store.
+    #[test]
+    fn test_raycast_grazing() {
+        let mut voxel_store = VoxelStore::new();
+        let sampler = Sampler::new(|_| Voxel::solid(1));
+        assert!(voxel_store.write(0..=7, 0..=0, 0..=7, sampler).is_ok());
+        let sampler = Sampler::new(|_| Voxel::solid(2));
+        assert!(voxel_store.write(7..=7, 3..=3, 7..=7, sampler).is_ok());
+
+        let along_face = Vec3::new(-1.0, 1.0, 0.5);
+        assert_eq!(voxel_store.raycast(along_face, Vec3::X, 100.0), None);
+
+        let below_face = Vec3::new(-1.0, 0.999, 0.5);
+        let hit = voxel_store.raycast(below_face, Vec3::X, 100.0).unwrap();
+        assert_eq!(hit.position, IVec3::new(0, 0, 0));
+        assert_eq!(hit.normal, IVec3::NEG_X);
+    }
+
+    // Test that diagonal rays skipping an unallocated chunk always advance past
+    // it, including rays whose exit point rounds to just inside the chunk, and
+    // hit the block beyond it.
+    #[test]
+    fn test_raycast_skips_unallocated_chunk() {
+        let mut voxel_store = VoxelStore::new();
+        let sampler = Sampler::new(|_| Voxel::solid(1));
+        assert!(voxel_store.write(8..=11, 8..=11, 8..=11, sampler).is_ok());
+        let sampler = Sampler::new(|_| Voxel::solid(2));
+        assert!(voxel_store.write(0..=0, 0..=0, 3..=3, sampler).is_ok());
+
+        for i in 0..1000 {
+            let offset = i as f32 * 0.0007;
+            let origin = Vec3::new(0.1 + offset, 0.3, 0.2 + offset * 0.5);
+            let direction = Vec3::new(1.0, 1.0 + offset * 0.1, 1.0 - offset * 0.2);
+            let hit = voxel_store.raycast(origin, direction, 100.0).unwrap();
+            assert_eq!(hit.voxel, Voxel::solid(1));
+            assert!(hit.position.cmpge(IVec3::splat(8)).all());
+            assert!(hit.position.cmple(IVec3::splat(11)).all());
+        }
+    }
+
+    // Test that dirty chunks are only returned once, and that a later edit
+    // returns the chunk it touched, at the chunk's origin, along with its
+    // allocated neighbors.
+    #[test]
+    fn test_take_dirty_chunks() {
+        let mut voxel_store = VoxelStore::new();
+        let sampler = Sampler::new(|_| Voxel::solid(1));
+        assert!(voxel_store.write(-4..=7, 0..=3, 0..=3, sampler).is_ok());
+        assert_eq!(voxel_store.take_dirty_chunks().len(), 3);
+        assert!(voxel_store.take_dirty_chunks().is_empty());
+
+        let sampler = Sampler::new(|_| Voxel::solid(2));
+        assert!(voxel_store.write(-1..=-1, 2..=2, 0..=0, sampler).is_ok());
+        let mut dirty = voxel_store.take_dirty_chunks();
+        dirty.sort_by_key(|(origin, _)| origin.x);
+        assert_eq!(dirty.len(), 2);
+        assert_eq!(dirty[0].0, IVec3::new(-4, 0, 0));
+        assert_eq!(dirty[0].1.get(UVec3::new(3, 2, 0)), Voxel::solid(2));
+        assert_eq!(dirty[1].0, IVec3::ZERO);
     }
 }
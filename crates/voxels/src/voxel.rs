@@ -2,35 +2,228 @@
 // ▀▄▀ ▀▄▀ █ █ █▄▄ █▄▄ ▄ █▀▄ ▄██
 
 //! The `voxel` module contains the [`Voxel`] type and all related methods. The
-//! [`Voxel`] type is just a wrapper for any 32-bit value, and provides many
-//! functions for easy conversion between other 32-bit values and [`Voxel`]s.
-
-/// A `Voxel` value is simply a 32-bit container for any data the user of this
-/// crate wishes to store. The `Voxel` type implements conversions to/from `u32`
-/// types, and in future should have conversions for several more types,
-/// including `f32`. Note that `Voxel` values must occupy 32-bits. There are no
-/// smaller or larger voxel allocations supported by this crate.
-#[derive(Clone, Copy, Default)]
+//! [`Voxel`] type carries a material id and a density, and provides functions
+//! for easy conversion between its packed 32-bit form and [`Voxel`]s.
+//!
+//! The module also contains the [`VoxelKind`] trait, which describes how any
+//! voxel type is meshed, so that meshing code is not tied to [`Voxel`].
+
+/// A `Voxel` value describes the content of a single cell of the voxel store.
+/// It carries a 16-bit material id, which is left for the user of this crate to
+/// interpret, and an 8-bit density. A voxel with zero density is air, no matter
+/// its material, and any other voxel is considered solid.
+///
+/// The `Voxel` type implements conversions to/from `u32` types, packing the
+/// material into the low 16 bits and the density into the next 8 bits. Note
+/// that `Voxel` values must fit in 32-bits. There are no larger voxel
+/// allocations supported by this crate.
+///
+/// The default `Voxel` is air.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
 pub struct Voxel {
-    value: u32,
+    material: u16,
+    density: u8,
 }
 
 impl Voxel {
-    /// Creates a new `Voxel` with its data set to the bits of the `u32` passed
-    /// to it.
-    pub fn new(value: u32) -> Self {
-        Self { value }
+    /// Creates a new `Voxel` with the given material id and density.
+    pub fn new(material: u16, density: u8) -> Self {
+        Self { material, density }
+    }
+
+    /// Creates an empty `Voxel`, with no material and zero density.
+    pub fn air() -> Self {
+        Self::default()
+    }
+
+    /// Creates a `Voxel` of the given material at full density.
+    pub fn solid(material: u16) -> Self {
+        Self {
+            material,
+            density: u8::MAX,
+        }
+    }
+
+    /// Returns the material id of this `Voxel`.
+    pub fn material(&self) -> u16 {
+        self.material
+    }
+
+    /// Returns the density of this `Voxel`, where zero is air and `u8::MAX` is
+    /// fully solid.
+    pub fn density(&self) -> u8 {
+        self.density
+    }
+
+    /// Returns whether this `Voxel` is air, which is true when its density is
+    /// zero.
+    pub fn is_air(&self) -> bool {
+        self.density == 0
+    }
+}
+
+/// The `VoxelVisibility` enum describes how a voxel affects the faces of the
+/// voxels around it when meshing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VoxelVisibility {
+    /// The voxel has no faces, and never hides the faces of its neighbors.
+    Empty,
+    /// The voxel has faces, but its neighbors can be seen through it, such as
+    /// with water or glass.
+    Translucent,
+    /// The voxel has faces, and hides the faces of its neighbors.
+    Opaque,
+}
+
+/// The `VoxelKind` trait is implemented by any type which can be meshed as a
+/// voxel. It is implemented for [`Voxel`], where air is empty and every other
+/// voxel is opaque, and for `bool`, where `true` is opaque, so that a closure
+/// choosing which voxels are solid can be used wherever a `VoxelKind` is
+/// expected.
+pub trait VoxelKind: Copy {
+    /// The value compared between two translucent voxels that touch. The face
+    /// between them is only meshed when their merge values differ, so that the
+    /// inside of a body of water is not meshed, but its boundary with glass is.
+    type MergeValue: PartialEq;
+
+    /// Returns how this voxel affects the faces of the voxels around it.
+    fn visibility(&self) -> VoxelVisibility;
+
+    /// Returns the value compared to decide whether two translucent voxels
+    /// merge into one body.
+    fn merge_value(&self) -> Self::MergeValue;
+
+    /// Returns the material id of this voxel, which is left for the user of
+    /// this crate to interpret.
+    fn material(&self) -> u16;
+
+    /// Returns whether the face of this voxel which touches `neighbor` needs to
+    /// be meshed. Empty voxels have no faces, and a face is hidden by an opaque
+    /// neighbor, or by a translucent neighbor of the same merge value.
+    fn face_visible(&self, neighbor: &Self) -> bool {
+        match (self.visibility(), neighbor.visibility()) {
+            (VoxelVisibility::Empty, _) | (_, VoxelVisibility::Opaque) => false,
+            (_, VoxelVisibility::Empty) => true,
+            (VoxelVisibility::Opaque, VoxelVisibility::Translucent) => true,
+            (VoxelVisibility::Translucent, VoxelVisibility::Translucent) => {
+                self.merge_value() != neighbor.merge_value()
+            }
+        }
+    }
+}
+
+impl VoxelKind for Voxel {
+    type MergeValue = u16;
+
+    fn visibility(&self) -> VoxelVisibility {
+        if self.is_air() {
+            VoxelVisibility::Empty
+        } else {
+            VoxelVisibility::Opaque
+        }
+    }
+
+    fn merge_value(&self) -> u16 {
+        self.material
+    }
+
+    fn material(&self) -> u16 {
+        self.material
+    }
+}
+
+impl VoxelKind for bool {
+    type MergeValue = bool;
+
+    fn visibility(&self) -> VoxelVisibility {
+        if *self {
+            VoxelVisibility::Opaque
+        } else {
+            VoxelVisibility::Empty
+        }
+    }
+
+    fn merge_value(&self) -> bool {
+        *self
+    }
+
+    fn material(&self) -> u16 {
+        0
     }
 }
 
 impl From<u32> for Voxel {
     fn from(value: u32) -> Self {
-        Self { value }
+        Self {
+            material: value as u16,
+            density: (value >> 16) as u8,
+        }
     }
 }
 
 impl Into<u32> for Voxel {
     fn into(self) -> u32 {
-        self.value
+        self.material as u32 | (self.density as u32) << 16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Voxel, VoxelKind, VoxelVisibility};
+
+    // Test that air can be told apart from solid voxels, including a solid voxel
+    // of the zero material.
+    #[test]
+    fn test_air_is_not_solid() {
+        assert!(Voxel::air().is_air());
+        assert!(!Voxel::solid(0).is_air());
+        assert_ne!(Voxel::air(), Voxel::solid(0));
+    }
+
+    // Test that a voxel survives being packed into a `u32` and back.
+    #[test]
+    fn test_u32_round_trip() {
+        let voxel = Voxel::new(513, 7);
+        let packed: u32 = voxel.into();
+        assert_eq!(Voxel::from(packed), voxel);
+    }
+
+    // Test that faces are hidden by opaque neighbors, and between translucent
+    // neighbors only when they merge.
+    #[test]
+    fn test_face_visible() {
+        #[derive(Clone, Copy)]
+        enum Block {
+            Air,
+            Stone,
+            Water,
+            Glass,
+        }
+        impl VoxelKind for Block {
+            type MergeValue = u8;
+            fn visibility(&self) -> VoxelVisibility {
+                match self {
+                    Block::Air => VoxelVisibility::Empty,
+                    Block::Stone => VoxelVisibility::Opaque,
+                    Block::Water | Block::Glass => VoxelVisibility::Translucent,
+                }
+            }
+            fn merge_value(&self) -> u8 {
+                *self as u8
+            }
+            fn material(&self) -> u16 {
+                *self as u16
+            }
+        }
+
+        assert!(Block::Stone.face_visible(&Block::Air));
+        assert!(Block::Stone.face_visible(&Block::Water));
+        assert!(!Block::Stone.face_visible(&Block::Stone));
+        assert!(!Block::Air.face_visible(&Block::Air));
+        assert!(!Block::Water.face_visible(&Block::Stone));
+        assert!(!Block::Water.face_visible(&Block::Water));
+        assert!(Block::Water.face_visible(&Block::Glass));
+        assert!(Voxel::solid(1).face_visible(&Voxel::air()));
+        assert!(!true.face_visible(&true));
     }
 }